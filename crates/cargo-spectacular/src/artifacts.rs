@@ -0,0 +1,463 @@
+use crate::diagnostics::{self, Diagnostic, Spinner};
+use crate::meta::{self, TestMeta};
+use crate::runner::{RunConfig, cargo_test};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// A line of `cargo --message-format=json` output, discriminated by `"reason"`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "reason")]
+enum CargoMessage {
+    #[serde(rename = "compiler-artifact")]
+    CompilerArtifact(Artifact),
+    #[serde(rename = "compiler-message")]
+    CompilerMessage { message: Diagnostic },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct Artifact {
+    manifest_path: String,
+    target: ArtifactTarget,
+    profile: Profile,
+    #[serde(default)]
+    executable: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ArtifactTarget {
+    kind: Vec<String>,
+    name: String,
+    #[serde(default)]
+    doctest: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Profile {
+    test: bool,
+}
+
+/// The kind of cargo target a test binary was built from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetKind {
+    Lib,
+    Bin,
+    Test,
+    Bench,
+    Example,
+    /// A library's doc-tests, run by rustdoc rather than a test binary.
+    Doc,
+}
+
+impl TargetKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TargetKind::Lib => "lib",
+            TargetKind::Bin => "bin",
+            TargetKind::Test => "test",
+            TargetKind::Bench => "bench",
+            TargetKind::Example => "example",
+            TargetKind::Doc => "doc",
+        }
+    }
+}
+
+/// A cargo target with tests, identified well enough to run it again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Target {
+    pub manifest_path: String,
+    pub kind: TargetKind,
+    pub name: String,
+}
+
+impl Target {
+    /// A `cargo test` command that selects exactly this target.
+    pub fn cargo_test(&self) -> Command {
+        let mut cmd = Command::new("cargo");
+        cmd.args(["test", "--manifest-path", &self.manifest_path]);
+        match self.kind {
+            TargetKind::Lib => cmd.arg("--lib"),
+            TargetKind::Bin => cmd.args(["--bin", &self.name]),
+            TargetKind::Test => cmd.args(["--test", &self.name]),
+            TargetKind::Bench => cmd.args(["--bench", &self.name]),
+            TargetKind::Example => cmd.args(["--example", &self.name]),
+            TargetKind::Doc => cmd.arg("--doc"),
+        };
+        cmd
+    }
+
+    /// A shell command that runs just `test` from this target, for copying.
+    ///
+    /// The manifest path is given relative to the current directory, and left
+    /// out when it is the manifest cargo would find there anyway.
+    pub fn rerun_command(&self, test: &str) -> String {
+        let mut words = vec![String::from("cargo"), String::from("test")];
+        let manifest = Path::new(&self.manifest_path);
+        let relative = std::env::current_dir()
+            .ok()
+            .and_then(|cwd| manifest.strip_prefix(cwd).ok().map(Path::to_path_buf));
+        match relative {
+            Some(path) if path == Path::new("Cargo.toml") => {}
+            Some(path) => {
+                words.extend([String::from("--manifest-path"), path.display().to_string()])
+            }
+            None => words.extend([String::from("--manifest-path"), self.manifest_path.clone()]),
+        }
+        match self.kind {
+            TargetKind::Lib => words.push(String::from("--lib")),
+            TargetKind::Bin => words.extend([String::from("--bin"), self.name.clone()]),
+            TargetKind::Test => words.extend([String::from("--test"), self.name.clone()]),
+            TargetKind::Bench => words.extend([String::from("--bench"), self.name.clone()]),
+            TargetKind::Example => words.extend([String::from("--example"), self.name.clone()]),
+            TargetKind::Doc => words.push(String::from("--doc")),
+        }
+        words.push(String::from("--"));
+        if self.takes_exact() {
+            words.push(String::from("--exact"));
+        }
+        words.push(test.to_string());
+        words
+            .iter()
+            .map(|word| shell_quote(word))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Whether `--exact` finds this target's tests by their full names.
+    ///
+    /// Doc-tests match nothing under `--exact`, so they are picked out by
+    /// their names as plain filters instead; those are specific enough.
+    pub fn takes_exact(&self) -> bool {
+        self.kind != TargetKind::Doc
+    }
+
+    /// List a library's doc-tests (`cargo test --doc -- --list --format terse`).
+    pub fn list_doc_tests(&self) -> io::Result<Vec<String>> {
+        let output = self
+            .cargo_test()
+            .args(["--", "--list", "--format", "terse"])
+            .stderr(Stdio::null())
+            .output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout
+            .lines()
+            .filter_map(|line| line.strip_suffix(": test"))
+            .map(String::from)
+            .collect())
+    }
+
+    /// The name sections and reports use for this target: its cargo target name,
+    /// with the kind added for anything other than a library or integration test.
+    pub fn label(&self) -> String {
+        match self.kind {
+            TargetKind::Lib | TargetKind::Test => self.name.clone(),
+            TargetKind::Bin => format!("{} (bin)", self.name),
+            TargetKind::Bench => format!("{} (bench)", self.name),
+            TargetKind::Example => format!("{} (example)", self.name),
+            TargetKind::Doc => format!("{} doc-tests", self.name),
+        }
+    }
+}
+
+/// Quote `word` for a POSIX shell, unless it is plainly safe as it is.
+fn shell_quote(word: &str) -> String {
+    let safe = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-:./=@+".contains(c));
+    if safe {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// The workspace, as `cargo metadata --no-deps` describes it.
+#[derive(Debug, Deserialize)]
+pub struct Workspace {
+    pub workspace_root: PathBuf,
+    pub target_directory: PathBuf,
+    pub packages: Vec<Package>,
+    /// The `[workspace.metadata]` table.
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Package {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    /// The `[package.metadata]` table.
+    #[serde(default)]
+    pub metadata: serde_json::Value,
+}
+
+/// Ask cargo about the workspace `config` points at.
+pub fn workspace(config: &RunConfig) -> io::Result<Workspace> {
+    let mut cmd = Command::new("cargo");
+    cmd.args(["metadata", "--no-deps", "--format-version", "1"]);
+    if let Some(ref path) = config.manifest_path {
+        cmd.args(["--manifest-path", path]);
+    }
+    let output = cmd.stderr(Stdio::inherit()).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other("cargo metadata failed"));
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// A compiled test executable, as reported by cargo.
+#[derive(Debug, Clone)]
+pub struct TestBinary {
+    pub target: Target,
+    pub executable: PathBuf,
+    /// Whether the package's library has doc-tests enabled (lib targets only).
+    pub doctest: bool,
+    /// Spectacular metadata embedded in the executable.
+    pub metadata: Vec<TestMeta>,
+}
+
+impl TestBinary {
+    /// The doc-tests of this binary's library.
+    pub fn doc_target(&self) -> Target {
+        Target {
+            kind: TargetKind::Doc,
+            ..self.target.clone()
+        }
+    }
+
+    /// List the binary's tests without running them (`--list --format terse`).
+    pub fn list_tests(&self) -> io::Result<Vec<String>> {
+        let output = Command::new(&self.executable)
+            .args(["--list", "--format", "terse"])
+            .stderr(Stdio::inherit())
+            .output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout
+            .lines()
+            .filter_map(|line| line.strip_suffix(": test"))
+            .map(String::from)
+            .collect())
+    }
+}
+
+/// The outcome of building the test targets.
+pub struct Build {
+    pub success: bool,
+    pub binaries: Vec<TestBinary>,
+    /// Compiler errors and warnings, each reported once.
+    pub errors: Vec<Diagnostic>,
+    pub warnings: Vec<Diagnostic>,
+    /// Anything else cargo printed that isn't a status line.
+    pub output: Vec<String>,
+}
+
+/// Build every selected test target without running it, and collect the executables.
+///
+/// Runs `cargo test --no-run --message-format=json` with the same package
+/// selection as a normal run, so the following `cargo test` invocations are fresh.
+/// Each executable is scanned for spectacular metadata. Cargo's own output is
+/// kept off the terminal: a progress line stands in for it, and diagnostics
+/// are collected for the caller to report.
+pub fn build(config: &RunConfig) -> io::Result<Build> {
+    let mut cmd = cargo_test(config);
+    let format = if config.color.stderr() {
+        "--message-format=json-diagnostic-rendered-ansi"
+    } else {
+        "--message-format=json"
+    };
+    cmd.args(["--no-run", format]);
+
+    let (reader, writer) = io::pipe()?;
+    cmd.stdout(writer.try_clone()?);
+    cmd.stderr(writer);
+
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to spawn cargo test: {e}")))?;
+    // The command holds the pipe's write ends; drop them so the reader sees EOF
+    drop(cmd);
+
+    let mut spinner = Spinner::new();
+    let mut build = Build {
+        success: false,
+        binaries: Vec::new(),
+        errors: Vec::new(),
+        warnings: Vec::new(),
+        output: Vec::new(),
+    };
+    let mut seen = HashSet::new();
+
+    for line in BufReader::new(reader).lines() {
+        let line = line?;
+        let message = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(_) if diagnostics::is_status_line(&line) => {
+                spinner.status(&line);
+                continue;
+            }
+            Err(_) => {
+                build.output.push(line);
+                continue;
+            }
+        };
+
+        let artifact = match message {
+            CargoMessage::CompilerArtifact(artifact) => artifact,
+            CargoMessage::CompilerMessage { message } => {
+                if !seen.insert(message.key()) {
+                    continue;
+                }
+                if message.is_error() {
+                    build.errors.push(message);
+                } else if message.is_warning() {
+                    build.warnings.push(message);
+                }
+                continue;
+            }
+            CargoMessage::Other => continue,
+        };
+        spinner.artifact();
+
+        if !artifact.profile.test {
+            continue;
+        }
+        let Some(executable) = artifact.executable else {
+            continue;
+        };
+        let Some(kind) = target_kind(&artifact.target.kind) else {
+            continue;
+        };
+        let executable = PathBuf::from(executable);
+        build.binaries.push(TestBinary {
+            target: Target {
+                manifest_path: artifact.manifest_path,
+                kind,
+                name: artifact.target.name,
+            },
+            metadata: meta::scan(&executable)?,
+            executable,
+            doctest: kind == TargetKind::Lib && artifact.target.doctest,
+        });
+    }
+
+    spinner.clear();
+    build.success = child.wait()?.success();
+    Ok(build)
+}
+
+/// Whether `line` is one of cargo's `--message-format=json` messages.
+pub fn is_cargo_message(line: &str) -> bool {
+    line.starts_with("{\"reason\":")
+}
+
+/// The test binary cargo is about to run, from one of its status lines.
+pub struct Running {
+    pub label: String,
+    /// `None` when the executable wasn't part of the build step.
+    pub target: Option<Target>,
+}
+
+/// Recognise `Running <target> (<executable>)` and `Doc-tests <crate>`.
+///
+/// Executables missing from `binaries` are named after their file, minus the hash.
+pub fn running(line: &str, binaries: &[TestBinary]) -> Option<Running> {
+    let line = diagnostics::strip_ansi(line);
+    let line = line.trim();
+
+    if let Some(krate) = line.strip_prefix("Doc-tests ") {
+        let target = binaries
+            .iter()
+            .find(|b| b.doctest && b.target.name == krate)
+            .map(TestBinary::doc_target);
+        return Some(Running {
+            label: format!("{krate} doc-tests"),
+            target,
+        });
+    }
+    let rest = line.strip_prefix("Running ")?;
+    let executable = rest.rsplit_once('(')?.1.strip_suffix(')')?;
+    let file_name = Path::new(executable).file_name()?;
+
+    match binaries
+        .iter()
+        .find(|b| b.executable.file_name() == Some(file_name))
+    {
+        Some(binary) => Some(Running {
+            label: binary.target.label(),
+            target: Some(binary.target.clone()),
+        }),
+        None => {
+            let stem = Path::new(file_name).file_stem()?.to_str()?;
+            Some(Running {
+                label: stem
+                    .rsplit_once('-')
+                    .map_or(stem, |(name, _)| name)
+                    .to_string(),
+                target: None,
+            })
+        }
+    }
+}
+
+fn target_kind(kinds: &[String]) -> Option<TargetKind> {
+    kinds.iter().find_map(|k| match k.as_str() {
+        "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro" => Some(TargetKind::Lib),
+        "bin" => Some(TargetKind::Bin),
+        "test" => Some(TargetKind::Test),
+        "bench" => Some(TargetKind::Bench),
+        "example" => Some(TargetKind::Example),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target(kind: TargetKind, name: &str) -> Target {
+        let manifest = std::env::current_dir().unwrap().join("Cargo.toml");
+        Target {
+            manifest_path: manifest.display().to_string(),
+            kind,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn rerun_command_selects_the_target_and_test() {
+        let test = target(TargetKind::Test, "auth");
+        assert_eq!(
+            test.rerun_command("login::rejects bad passwords"),
+            "cargo test --test auth -- --exact 'login::rejects bad passwords'"
+        );
+        let bin = target(TargetKind::Bin, "server");
+        assert_eq!(
+            bin.rerun_command("tests::boots"),
+            "cargo test --bin server -- --exact tests::boots"
+        );
+    }
+
+    #[test]
+    fn doc_tests_are_rerun_without_exact() {
+        let doc = target(TargetKind::Doc, "demo");
+        assert!(!doc.takes_exact());
+        assert_eq!(
+            doc.rerun_command("src/lib.rs - add_one (line 3)"),
+            "cargo test --doc -- 'src/lib.rs - add_one (line 3)'"
+        );
+    }
+
+    #[test]
+    fn labels_name_the_kind() {
+        assert_eq!(target(TargetKind::Lib, "demo").label(), "demo");
+        assert_eq!(target(TargetKind::Test, "auth").label(), "auth");
+        assert_eq!(target(TargetKind::Bin, "server").label(), "server (bin)");
+        assert_eq!(target(TargetKind::Doc, "demo").label(), "demo doc-tests");
+    }
+}
@@ -0,0 +1,295 @@
+use std::path::Path;
+
+/// How much backtrace failing tests print (`--backtrace`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backtrace {
+    /// `RUST_BACKTRACE=1`: symbol names and source locations.
+    Short,
+    /// `RUST_BACKTRACE=full`: with addresses and absolute paths.
+    Full,
+}
+
+impl Backtrace {
+    pub fn parse(name: &str) -> Option<Backtrace> {
+        match name {
+            "short" => Some(Backtrace::Short),
+            "full" => Some(Backtrace::Full),
+            _ => None,
+        }
+    }
+
+    /// The `RUST_BACKTRACE` value the test processes run with.
+    pub fn env_value(self) -> &'static str {
+        match self {
+            Backtrace::Short => "1",
+            Backtrace::Full => "full",
+        }
+    }
+}
+
+/// Symbols of the code spectacular generates around a test body.
+const WRAPPERS: [&str; 3] = ["catch_unwind", "__spec_", "spectacular::__internal::"];
+
+/// One frame of a backtrace: `N: symbol` and the `at file:line:col` lines under it.
+struct Frame<'a> {
+    header: &'a str,
+    symbol: &'a str,
+    locations: Vec<&'a str>,
+}
+
+/// `stdout` with its backtrace cut down to the frames in the user's code.
+///
+/// Frames from std, libtest, dependencies and spectacular's generated
+/// wrappers are dropped, and paths are shown relative to `root`, the
+/// workspace of the package at `manifest_path`. When no frame is left, as
+/// for a panic inside a dependency, the backtrace is kept whole.
+pub fn filter(stdout: &str, manifest_path: &Path, root: &Path) -> String {
+    let lines: Vec<&str> = stdout.lines().collect();
+    let Some(start) = lines.iter().position(|l| l.trim() == "stack backtrace:") else {
+        return stdout.to_string();
+    };
+    let package = manifest_path.parent().unwrap_or(Path::new("."));
+
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut end = start + 1;
+    while let Some(line) = lines.get(end) {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("at ")
+            && let Some(frame) = frames.last_mut()
+        {
+            frame.locations.push(line);
+        } else if let Some(symbol) = frame_symbol(trimmed) {
+            frames.push(Frame {
+                header: line,
+                symbol,
+                locations: Vec::new(),
+            });
+        } else {
+            break;
+        }
+        end += 1;
+    }
+
+    let mut kept: Vec<String> = Vec::new();
+    for (i, frame) in frames.iter().enumerate() {
+        let symbol = without_hash(frame.symbol);
+        if WRAPPERS.iter().any(|w| symbol.contains(w)) {
+            continue;
+        }
+        if let Some(callee) = i.checked_sub(1).map(|j| without_hash(frames[j].symbol)) {
+            // The generated test function that runs the body under `catch_unwind`
+            if callee.contains("catch_unwind") {
+                continue;
+            }
+            // The closure libtest's `#[test]` calls the test function from
+            if closure_parent(symbol) == Some(callee) {
+                continue;
+            }
+        }
+        let locations: Vec<String> = frame
+            .locations
+            .iter()
+            .filter_map(|line| relative_location(line, package, root))
+            .collect();
+        if locations.is_empty() {
+            continue;
+        }
+        kept.push(frame.header.to_string());
+        kept.extend(locations);
+    }
+    if kept.is_empty() {
+        return stdout.to_string();
+    }
+
+    let mut out: Vec<String> = lines[..=start].iter().map(|l| l.to_string()).collect();
+    out.extend(kept);
+    out.extend(lines[end..].iter().map(|l| l.to_string()));
+    let mut out = out.join("\n");
+    if stdout.ends_with('\n') {
+        out.push('\n');
+    }
+    out
+}
+
+/// The symbol of a `  4: symbol` or `  4:  0x5609 - symbol` frame line.
+fn frame_symbol(line: &str) -> Option<&str> {
+    let (index, rest) = line.split_once(':')?;
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let rest = rest.trim();
+    Some(match rest.split_once(" - ") {
+        Some((address, symbol)) if address.starts_with("0x") => symbol,
+        _ => rest,
+    })
+}
+
+/// The function a closure is declared in: `a::b` for `a::b::{{closure}}`,
+/// or `a::b::{closure#0}` as newer toolchains print it.
+fn closure_parent(symbol: &str) -> Option<&str> {
+    let (parent, last) = symbol.rsplit_once("::")?;
+    (last == "{{closure}}" || last.starts_with("{closure#")).then_some(parent)
+}
+
+/// The symbol without the `::h0123456789abcdef` hash of full backtraces.
+fn without_hash(symbol: &str) -> &str {
+    match symbol.rsplit_once("::h") {
+        Some((name, hash)) if hash.len() == 16 && hash.bytes().all(|b| b.is_ascii_hexdigit()) => {
+            name
+        }
+        _ => symbol,
+    }
+}
+
+/// An `at file:line:col` line with the file relative to `root`, or `None`
+/// when the file is outside the workspace.
+///
+/// std prints files under the test's working directory, its package, as
+/// `./file`, and any other file in full.
+fn relative_location(line: &str, package: &Path, root: &Path) -> Option<String> {
+    let (indent, location) = line.split_once("at ")?;
+    let mut parts = location.trim_end().rsplitn(3, ':');
+    let column = parts.next()?;
+    let number = parts.next()?;
+    let file = parts.next()?;
+    // Joining an absolute path replaces the package directory
+    let path = package.join(file.strip_prefix("./").unwrap_or(file));
+    let relative = path.strip_prefix(root).ok()?;
+    Some(format!(
+        "{indent}at {}:{number}:{column}",
+        relative.display()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = "/work/crates/bt/Cargo.toml";
+
+    /// A short backtrace from a test with an `after_each` hook, as std
+    /// prints it with the package as the working directory.
+    const SHORT: &str = "\
+thread 'hooks::fails_in_the_library' panicked at src/lib.rs:1:24:
+boom
+stack backtrace:
+   0: __rustc::rust_begin_unwind
+             at /rustc/5980/library/std/src/panicking.rs:689:5
+   1: core::panicking::panic_fmt
+             at /rustc/5980/library/core/src/panicking.rs:80:14
+   2: bt::check
+             at ./src/lib.rs:1:24
+   3: hooks::hooks::fails_in_the_library::{{closure}}
+             at ./tests/hooks.rs:8:13
+   4: core::ops::function::FnOnce::call_once
+             at /rustc/5980/library/core/src/ops/function.rs:250:5
+   5: <core::panic::unwind_safe::AssertUnwindSafe<F> as core::ops::function::FnOnce<()>>::call_once
+             at /rustc/5980/library/core/src/panic/unwind_safe.rs:274:9
+   6: std::panicking::catch_unwind::do_call
+             at /rustc/5980/library/std/src/panicking.rs:581:40
+   7: __rust_try
+   8: std::panicking::catch_unwind
+             at /rustc/5980/library/std/src/panicking.rs:544:19
+   9: std::panic::catch_unwind
+             at /rustc/5980/library/std/src/panic.rs:359:14
+  10: hooks::hooks::fails_in_the_library
+             at ./tests/hooks.rs:3:1
+  11: hooks::hooks::fails_in_the_library::{{closure}}
+             at ./tests/hooks.rs:11:2
+  12: core::ops::function::FnOnce::call_once
+             at /rustc/5980/library/core/src/ops/function.rs:250:5
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.
+";
+
+    fn filter(stdout: &str) -> String {
+        super::filter(stdout, Path::new(MANIFEST), Path::new("/work"))
+    }
+
+    #[test]
+    fn keeps_only_the_frames_in_the_workspace() {
+        assert_eq!(
+            filter(SHORT),
+            "\
+thread 'hooks::fails_in_the_library' panicked at src/lib.rs:1:24:
+boom
+stack backtrace:
+   2: bt::check
+             at crates/bt/src/lib.rs:1:24
+   3: hooks::hooks::fails_in_the_library::{{closure}}
+             at crates/bt/tests/hooks.rs:8:13
+note: Some details are omitted, run with `RUST_BACKTRACE=full` for a verbose backtrace.
+"
+        );
+    }
+
+    #[test]
+    fn drops_dependencies_and_shortens_other_members() {
+        let stdout = "\
+stack backtrace:
+   0: serde_json::de::from_str
+             at /home/me/.cargo/registry/src/index-1/serde_json-1.0.0/src/de.rs:10:5
+   1: shared::load
+             at /work/crates/shared/src/lib.rs:4:9
+   2: bt::check
+             at ./src/lib.rs:1:24";
+        assert_eq!(
+            filter(stdout),
+            "\
+stack backtrace:
+   1: shared::load
+             at crates/shared/src/lib.rs:4:9
+   2: bt::check
+             at crates/bt/src/lib.rs:1:24"
+        );
+    }
+
+    #[test]
+    fn reads_full_backtraces() {
+        let stdout = "\
+stack backtrace:
+  19:     0x55f836a2c0ae - bt::check::ha5a7d294b9b3bb00
+                               at /work/crates/bt/src/lib.rs:1:24
+  25:     0x55f836a2bf26 - std::panicking::catch_unwind::h772e579141869d3c
+                               at /rustc/5980/library/std/src/panicking.rs:544:19
+  27:     0x55f836a2b684 - hooks::hooks::fails_in_the_library::hff3ddfe9dbe36d77
+                               at /work/crates/bt/tests/hooks.rs:3:1
+  28:     0x55f836a2b747 - hooks::hooks::fails_in_the_library::{{closure}}::h23eef1d184668a76
+                               at /work/crates/bt/tests/hooks.rs:11:2
+";
+        assert_eq!(
+            filter(stdout),
+            "\
+stack backtrace:
+  19:     0x55f836a2c0ae - bt::check::ha5a7d294b9b3bb00
+                               at crates/bt/src/lib.rs:1:24
+"
+        );
+    }
+
+    #[test]
+    fn keeps_a_backtrace_with_nothing_of_the_workspace_whole() {
+        let stdout = "\
+stack backtrace:
+   0: serde_json::de::from_str
+             at /home/me/.cargo/registry/src/index-1/serde_json-1.0.0/src/de.rs:10:5
+   1: __rust_try
+";
+        assert_eq!(filter(stdout), stdout);
+        assert_eq!(filter("boom\n"), "boom\n");
+    }
+
+    #[test]
+    fn frame_symbols_and_hashes() {
+        assert_eq!(frame_symbol("4: a::b"), Some("a::b"));
+        assert_eq!(
+            frame_symbol("4:     0x5609 - a::b::h0123456789abcdef"),
+            Some("a::b::h0123456789abcdef")
+        );
+        assert_eq!(frame_symbol("note: see"), None);
+        assert_eq!(without_hash("a::b::h0123456789abcdef"), "a::b");
+        assert_eq!(without_hash("a::hello"), "a::hello");
+        assert_eq!(closure_parent("a::b::{{closure}}"), Some("a::b"));
+        assert_eq!(closure_parent("a::b::{closure#0}"), Some("a::b"));
+        assert_eq!(closure_parent("a::b"), None);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::meta::{Catalog, TestMeta};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the file the `Bencher` adds measurements to.
+pub const RESULTS_ENV: &str = "SPECTACULAR_BENCH";
+
+/// One benchmark's measurement, in nanoseconds per iteration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Measurement {
+    /// Full module path, including the crate name, as in the test metadata.
+    pub path: String,
+    pub mean: f64,
+    pub deviation: f64,
+    pub iterations: u64,
+}
+
+impl Measurement {
+    /// The libtest name: the module path without its leading crate segment.
+    fn name(&self) -> &str {
+        self.path
+            .split_once("::")
+            .map_or(self.path.as_str(), |(_, rest)| rest)
+    }
+}
+
+/// The file this run's measurements go to, emptied for the run.
+pub fn start(dir: &Path) -> io::Result<PathBuf> {
+    let dir = dir.join("bench");
+    fs::create_dir_all(&dir)?;
+    let path = dir.join("results.jsonl");
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    Ok(path)
+}
+
+/// The measurements the benchmarks added to `path`, in the order they finished.
+pub fn read(path: &Path) -> io::Result<Vec<Measurement>> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(text
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn baseline_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join("bench").join(format!("{name}.json"))
+}
+
+/// Keep `measurements` under `name`, replacing the baseline of that name.
+pub fn save_baseline(dir: &Path, name: &str, measurements: &[Measurement]) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(measurements)?;
+    fs::write(baseline_path(dir, name), json + "\n")
+}
+
+/// The measurements saved under `name`.
+pub fn load_baseline(dir: &Path, name: &str) -> io::Result<Vec<Measurement>> {
+    let path = baseline_path(dir, name);
+    let json = fs::read_to_string(&path).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => io::Error::new(
+            e.kind(),
+            format!("no baseline named {name} (save one with --save-baseline {name})"),
+        ),
+        _ => e,
+    })?;
+    serde_json::from_str(&json).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    })
+}
+
+/// The measurements as a table, compared with the `baseline` when given.
+///
+/// A change is only called faster or slower when it is larger than the two
+/// deviations together; anything less is noise.
+pub fn write_table(
+    measurements: &[Measurement],
+    baseline: Option<(&str, &[Measurement])>,
+    catalog: &Catalog,
+    w: &mut dyn Write,
+) -> io::Result<()> {
+    if measurements.is_empty() {
+        writeln!(w, "No benchmarks were measured\n")?;
+        return w.flush();
+    }
+
+    let rows: Vec<(String, String, String)> = measurements
+        .iter()
+        .map(|m| {
+            let name = catalog
+                .by_path(&m.path)
+                .and_then(TestMeta::full_description)
+                .unwrap_or_else(|| m.name().to_string());
+            let time = format!("{} ± {}", format_time(m.mean), format_time(m.deviation));
+            let iterations = format!("{} iterations", m.iterations);
+            (name, time, iterations)
+        })
+        .collect();
+    let name_width = rows.iter().map(|r| r.0.chars().count()).max().unwrap_or(0);
+    let time_width = rows.iter().map(|r| r.1.chars().count()).max().unwrap_or(0);
+    let iter_width = rows.iter().map(|r| r.2.len()).max().unwrap_or(0);
+
+    match baseline {
+        Some((name, _)) => writeln!(w, "Benchmarks (compared with {name}):")?,
+        None => writeln!(w, "Benchmarks:")?,
+    }
+    for (m, (name, time, iterations)) in measurements.iter().zip(&rows) {
+        write!(
+            w,
+            "  {name:<name_width$}  {time:>time_width$}  {iterations:>iter_width$}"
+        )?;
+        if let Some((_, saved)) = baseline {
+            match saved.iter().find(|s| s.path == m.path) {
+                Some(base) => write!(w, "  {}", compare(m, base))?,
+                None => write!(w, "  new")?,
+            }
+        }
+        writeln!(w)?;
+    }
+    writeln!(w)?;
+    w.flush()
+}
+
+/// `+12.3% slower`, `-8.0% faster`, `+0.4% (noise)`
+fn compare(m: &Measurement, base: &Measurement) -> String {
+    if base.mean <= 0.0 {
+        return String::from("new");
+    }
+    let change = (m.mean - base.mean) / base.mean * 100.0;
+    let verdict = if (m.mean - base.mean).abs() <= m.deviation + base.deviation {
+        "(noise)"
+    } else if change > 0.0 {
+        "slower"
+    } else {
+        "faster"
+    };
+    format!("{change:+.1}% {verdict}")
+}
+
+/// Nanoseconds in the unit that keeps the number readable: `812.4 ns`, `51.22 µs`.
+fn format_time(ns: f64) -> String {
+    if ns < 1_000.0 {
+        format!("{ns:.1} ns")
+    } else if ns < 1_000_000.0 {
+        format!("{:.2} µs", ns / 1_000.0)
+    } else if ns < 1_000_000_000.0 {
+        format!("{:.2} ms", ns / 1_000_000.0)
+    } else {
+        format!("{:.2} s", ns / 1_000_000_000.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::scratch;
+
+    fn measurement(path: &str, mean: f64, deviation: f64) -> Measurement {
+        Measurement {
+            path: path.to_string(),
+            mean,
+            deviation,
+            iterations: 1000,
+        }
+    }
+
+    #[test]
+    fn times_in_a_readable_unit() {
+        assert_eq!(format_time(812.44), "812.4 ns");
+        assert_eq!(format_time(51_220.0), "51.22 µs");
+        assert_eq!(format_time(3_500_000.0), "3.50 ms");
+        assert_eq!(format_time(2_000_000_000.0), "2.00 s");
+    }
+
+    #[test]
+    fn changes_within_the_deviations_are_noise() {
+        let base = measurement("demo::parse", 100.0, 2.0);
+        assert_eq!(
+            compare(&measurement("demo::parse", 120.0, 2.0), &base),
+            "+20.0% slower"
+        );
+        assert_eq!(
+            compare(&measurement("demo::parse", 90.0, 2.0), &base),
+            "-10.0% faster"
+        );
+        assert_eq!(
+            compare(&measurement("demo::parse", 103.0, 2.0), &base),
+            "+3.0% (noise)"
+        );
+        assert_eq!(compare(&base, &measurement("demo::parse", 0.0, 0.0)), "new");
+    }
+
+    #[test]
+    fn the_table_compares_with_the_baseline() {
+        let now = [
+            measurement("demo::parse", 120.0, 1.0),
+            measurement("demo::render", 51_220.0, 300.0),
+        ];
+        let saved = [measurement("demo::parse", 100.0, 1.0)];
+        let mut out = Vec::new();
+        write_table(&now, Some(("main", &saved)), &Catalog::default(), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+Benchmarks (compared with main):
+  parse     120.0 ns ± 1.0 ns  1000 iterations  +20.0% slower
+  render  51.22 µs ± 300.0 ns  1000 iterations  new
+
+"
+        );
+    }
+
+    #[test]
+    fn measurements_and_baselines_on_disk() {
+        let dir = scratch("bench-disk");
+        let path = start(&dir).unwrap();
+        let line = serde_json::to_string(&measurement("demo::parse", 1.0, 0.0)).unwrap();
+        fs::write(&path, format!("{line}\nnot json\n")).unwrap();
+        let read = read(&path).unwrap();
+        save_baseline(&dir, "main", &read).unwrap();
+        let loaded = load_baseline(&dir, "main").unwrap();
+        let missing = load_baseline(&dir, "nope").unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(read.len(), 1);
+        assert_eq!(loaded[0].path, "demo::parse");
+        assert_eq!(
+            missing.to_string(),
+            "no baseline named nope (save one with --save-baseline nope)"
+        );
+    }
+}
@@ -0,0 +1,550 @@
+use crate::backtrace::Backtrace;
+use crate::color::{ColorMode, Palette, Theme};
+use crate::config;
+use crate::formatter::{self, Registry};
+use crate::runner::{self, BackendChoice, RunConfig};
+use crate::select::Shard;
+use crate::{artifacts, history, list, live, state, watch};
+use std::process::ExitCode;
+
+/// How many tests and groups `--profile` lists without a count.
+const DEFAULT_PROFILE_COUNT: usize = 10;
+
+/// Run `cargo spectacular` with the process's arguments, as the
+/// `cargo-spectacular` binary does.
+///
+/// `--format` picks from `registry`, so a binary that registers its own
+/// formatters gets every other flag, the configuration files, watch mode
+/// and the rest unchanged.
+pub fn main_with(registry: Registry) -> ExitCode {
+    let args: Vec<String> = std::env::args().collect();
+
+    let mut format_name: Option<String> = None;
+    let mut manifest_path: Option<String> = None;
+    let mut package: Option<String> = None;
+    let mut lib_only = false;
+    let mut all = false;
+    let mut tags: Vec<String> = Vec::new();
+    let mut exclude_tags: Vec<String> = Vec::new();
+    let mut no_exclude_tags = false;
+    let mut list_tags = false;
+    let mut junit: Option<String> = None;
+    let mut backend = BackendChoice::Auto;
+    let mut show_warnings = false;
+    let mut full_errors = false;
+    let mut watch = false;
+    let mut only_failures = false;
+    let mut shard: Option<Shard> = None;
+    let mut list = false;
+    let mut json = false;
+    let mut profile: Option<usize> = None;
+    let mut slowdown_factor = history::DEFAULT_FACTOR;
+    let mut fail_on_slowdown = false;
+    let mut live = false;
+    let mut hang_after = live::DEFAULT_HANG_AFTER;
+    let mut verbose = false;
+    let mut bench = false;
+    let mut baseline: Option<String> = None;
+    let mut save_baseline: Option<String> = None;
+    let mut backtrace: Option<Backtrace> = None;
+    let mut fail_fast = false;
+    let mut color_mode: Option<ColorMode> = None;
+    let mut theme: Option<Theme> = None;
+    let mut extra_args: Vec<String> = Vec::new();
+
+    let iter = args.iter().skip(1); // skip binary name
+    // Skip "spectacular" if invoked as `cargo spectacular`
+    let mut args_to_parse: Vec<&String> = Vec::new();
+    let mut skipped_subcommand = false;
+    for arg in iter {
+        if !skipped_subcommand && arg == "spectacular" {
+            skipped_subcommand = true;
+            continue;
+        }
+        args_to_parse.push(arg);
+    }
+
+    let mut i = 0;
+    let mut after_separator = false;
+    while i < args_to_parse.len() {
+        let arg = args_to_parse[i].as_str();
+
+        if after_separator {
+            extra_args.push(arg.to_string());
+            i += 1;
+            continue;
+        }
+
+        match arg {
+            "list" if i == 0 => {
+                list = true;
+            }
+            "--json" => {
+                json = true;
+            }
+            "--" => {
+                after_separator = true;
+            }
+            "--pride" => {
+                format_name = Some(String::from("pride"));
+            }
+            "--boring" => {
+                format_name = Some(String::from("boring"));
+            }
+            "--format" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    format_name = Some(args_to_parse[i].clone());
+                } else {
+                    eprintln!("Error: --format requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--manifest-path" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    manifest_path = Some(args_to_parse[i].clone());
+                } else {
+                    eprintln!("Error: --manifest-path requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--package" | "-p" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    package = Some(args_to_parse[i].clone());
+                } else {
+                    eprintln!("Error: --package requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--lib" => {
+                lib_only = true;
+            }
+            "--all" => {
+                all = true;
+            }
+            "--tag" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    tags.extend(split_tags(args_to_parse[i]));
+                } else {
+                    eprintln!("Error: --tag requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--exclude-tag" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    exclude_tags.extend(split_tags(args_to_parse[i]));
+                } else {
+                    eprintln!("Error: --exclude-tag requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--junit" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    junit = Some(args_to_parse[i].clone());
+                } else {
+                    eprintln!("Error: --junit requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--color" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    let name = args_to_parse[i];
+                    match ColorMode::parse(name) {
+                        Some(mode) => color_mode = Some(mode),
+                        None => {
+                            eprintln!(
+                                "Error: unknown color mode {name} (expected auto, always or never)"
+                            );
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                } else {
+                    eprintln!("Error: --color requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--theme" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    let name = args_to_parse[i];
+                    match Theme::parse(name) {
+                        Some(t) => theme = Some(t),
+                        None => {
+                            eprintln!(
+                                "Error: unknown theme {name} (expected default, colorblind or light)"
+                            );
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                } else {
+                    eprintln!("Error: --theme requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--backend" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    let name = args_to_parse[i];
+                    match BackendChoice::parse(name) {
+                        Some(b) => backend = b,
+                        None => {
+                            eprintln!(
+                                "Error: unknown backend {name} (expected auto, json or human)"
+                            );
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                } else {
+                    eprintln!("Error: --backend requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--show-warnings" => {
+                show_warnings = true;
+            }
+            "--full-errors" => {
+                full_errors = true;
+            }
+            "--watch" => {
+                watch = true;
+            }
+            "--profile" => {
+                // The count is optional: `--profile` alone lists ten
+                let count = args_to_parse
+                    .get(i + 1)
+                    .and_then(|next| next.parse::<usize>().ok());
+                if count.is_some() {
+                    i += 1;
+                }
+                profile = Some(count.unwrap_or(DEFAULT_PROFILE_COUNT));
+            }
+            "--slowdown-factor" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    match args_to_parse[i].parse::<f64>() {
+                        Ok(factor) if factor > 1.0 => slowdown_factor = factor,
+                        _ => {
+                            eprintln!("Error: --slowdown-factor must be a number above 1");
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                } else {
+                    eprintln!("Error: --slowdown-factor requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--fail-on-slowdown" => {
+                fail_on_slowdown = true;
+            }
+            "--shard" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    match Shard::parse(args_to_parse[i]) {
+                        Some(s) => shard = Some(s),
+                        None => {
+                            eprintln!("Error: --shard expects K/N with 1 <= K <= N, like 2/8");
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                } else {
+                    eprintln!("Error: --shard requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--live" => {
+                live = true;
+            }
+            "--hang-after" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    match args_to_parse[i].parse::<f64>() {
+                        Ok(secs) if secs > 0.0 => hang_after = secs,
+                        _ => {
+                            eprintln!("Error: --hang-after must be a number of seconds above 0");
+                            return ExitCode::FAILURE;
+                        }
+                    }
+                } else {
+                    eprintln!("Error: --hang-after requires a value");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--bench" => {
+                bench = true;
+            }
+            "--baseline" | "--save-baseline" => {
+                i += 1;
+                if i < args_to_parse.len() {
+                    let name = args_to_parse[i].clone();
+                    if arg == "--baseline" {
+                        baseline = Some(name);
+                    } else {
+                        save_baseline = Some(name);
+                    }
+                    bench = true;
+                } else {
+                    eprintln!("Error: {arg} requires a name");
+                    return ExitCode::FAILURE;
+                }
+            }
+            "--backtrace" => {
+                // The style is optional: `--backtrace` alone is short
+                let style = args_to_parse
+                    .get(i + 1)
+                    .and_then(|next| Backtrace::parse(next));
+                if style.is_some() {
+                    i += 1;
+                }
+                backtrace = Some(style.unwrap_or(Backtrace::Short));
+            }
+            "--fail-fast" => {
+                fail_fast = true;
+            }
+            "--verbose" | "-v" => {
+                verbose = true;
+            }
+            "--only-failures" => {
+                only_failures = true;
+            }
+            "--no-exclude-tags" => {
+                no_exclude_tags = true;
+            }
+            "--list-tags" => {
+                list_tags = true;
+            }
+            "--help" | "-h" => {
+                print_help(&registry);
+                return ExitCode::SUCCESS;
+            }
+            other => {
+                // Treat unknown args as extra test args
+                extra_args.push(other.to_string());
+            }
+        }
+        i += 1;
+    }
+
+    if json && !list {
+        eprintln!("Error: --json is only for `cargo spectacular list`");
+        return ExitCode::FAILURE;
+    }
+
+    let mut config = RunConfig {
+        manifest_path,
+        package,
+        lib_only,
+        all,
+        tags,
+        exclude_tags,
+        backend,
+        show_warnings,
+        full_errors,
+        profile,
+        slowdown_factor,
+        fail_on_slowdown,
+        shard,
+        only: None,
+        live,
+        hang_after,
+        verbose,
+        bench,
+        baseline,
+        save_baseline,
+        backtrace,
+        fail_fast,
+        color: ColorMode::Auto,
+        palette: Palette::new(Theme::Default),
+        extra_args,
+    };
+
+    // Project defaults fill in whatever the command line left out
+    let settings = artifacts::workspace(&config).and_then(|workspace| {
+        config::load(
+            &workspace,
+            config.package.as_deref(),
+            config.manifest_path.as_deref(),
+        )
+    });
+    let settings = match settings {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if config.exclude_tags.is_empty() && !no_exclude_tags {
+        config.exclude_tags = settings.exclude_tags_besides(&config.tags);
+    }
+    if config.extra_args.is_empty() {
+        config.extra_args = settings.test_args.unwrap_or_default();
+    }
+    config.profile = config.profile.or(settings.profile);
+    let format_name = format_name
+        .or(settings.format)
+        .unwrap_or_else(|| String::from("pride"));
+    let junit = junit.or(settings.junit);
+    // Auto mode drops color when stdout isn't a terminal
+    config.color = color_mode.or(settings.color).unwrap_or_default();
+    config.palette = Palette::new(theme.or(settings.theme).unwrap_or_default());
+    let color = config.palette();
+
+    let make_formatter = || {
+        let mut fmt = registry.create(&format_name, color)?;
+        if let Some(ref path) = junit {
+            let report = Box::new(formatter::junit::JunitReport::new(path));
+            fmt = Box::new(formatter::Fanout::new(fmt).with(report));
+        }
+        Ok(fmt)
+    };
+
+    if only_failures {
+        match state::dir(&config).and_then(|dir| state::load_failures(&dir)) {
+            Ok(Some(failures)) if !failures.is_empty() => config.only = Some(failures),
+            Ok(Some(_)) => {
+                println!("No failures in the last run");
+                return ExitCode::SUCCESS;
+            }
+            Ok(None) => {
+                eprintln!("Error: no previous run recorded; run the tests first");
+                return ExitCode::FAILURE;
+            }
+            Err(e) => {
+                eprintln!("Error: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let result = if list {
+        list::list(&config, json, &mut std::io::stdout().lock())
+    } else if list_tags {
+        runner::list_tags(&config, &mut std::io::stdout().lock())
+    } else if watch {
+        watch::watch(&config, make_formatter)
+    } else {
+        make_formatter().and_then(|mut fmt| {
+            runner::run(&config, fmt.as_mut(), &mut std::io::stdout().lock())
+                .map(|result| result.exit_code())
+        })
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Split a `--tag` value on commas: `--tag slow,db` is `--tag slow --tag db`.
+fn split_tags(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(String::from)
+}
+
+fn print_help(registry: &Registry) {
+    let formats = registry.names().collect::<Vec<_>>().join(", ");
+    println!(
+        "\
+cargo-spectacular — custom test output formatter
+
+USAGE:
+    cargo spectacular [OPTIONS] [-- <TEST_ARGS>...]
+    cargo spectacular list [--json] [OPTIONS] [-- <FILTERS>...]
+
+COMMANDS:
+    list                      List the tests as a tree of binaries, groups and tests
+
+OPTIONS:
+    --pride                   Rainbow dots output (default)
+    --boring                  Plain dots, colored summary
+    --format <NAME>           Output format, one of FORMATS below
+    --manifest-path <PATH>    Path to Cargo.toml
+    --package, -p <PKG>       Run tests for a specific package
+    --lib                     Test only the library
+    --all                     Test all packages in the workspace
+    --tag <TAG>               Run only tests with this tag (repeatable, any match)
+    --exclude-tag <TAG>       Skip tests with this tag (repeatable)
+    --no-exclude-tags         Ignore the configured exclude-tags
+    --list-tags               List all tags with their test counts and exit
+    --shard <K/N>             Run only the K-th of N slices of the tests
+    --junit <PATH>            Also write a JUnit XML report to PATH
+    --backend <BACKEND>       How to read test results: auto, json or human (default: auto)
+    --color <WHEN>            When to use color: auto, always or never (default: auto)
+    --theme <NAME>            Colors: default, colorblind or light
+    --show-warnings           Print build warnings instead of counting them
+    --full-errors             Print compile errors as rustc renders them
+    --watch                   Re-run the tests when src/, tests/ or Cargo.toml change
+    --only-failures           Re-run only the tests that failed in the last run
+    --profile [N]             List the N slowest tests and groups (default: 10)
+    --slowdown-factor <F>     Call a test slower than usual past F times its median (default: 2)
+    --fail-on-slowdown        Fail the run when a test is slower than usual
+    --live                    Show the tests still running under the output
+    --hang-after <SECS>       With --live, highlight tests running longer (default: 10)
+    --bench                   Run only the benchmarks, in release mode, and measure them
+    --save-baseline <NAME>    Measure the benchmarks and save them as baseline NAME
+    --baseline <NAME>         Measure the benchmarks and compare them with baseline NAME
+    --backtrace [short|full]  Print backtraces for failures, only the frames in your code
+    --fail-fast               Stop at the first failing test
+    -v, --verbose             Also print cargo's status lines and unknown libtest output
+    --json                    With list: print JSON instead of a tree
+    -h, --help                Print this help message
+
+ARGS:
+    <TEST_ARGS>...            Extra arguments passed to the test binary
+
+FORMATS:
+    {formats}
+    exec:<COMMAND>, or any NAME with a spectacular-formatter-NAME on PATH
+
+See the test runner guide in docs/ for how each option works.
+
+EXAMPLES:
+    cargo spectacular                          # pride (default)
+    cargo spectacular --boring                 # plain dots, colored summary
+    cargo spectacular --format documentation   # tree of groups and tests
+    cargo spectacular --format progress        # one bar for the run, failures as they happen
+    cargo spectacular -- test_name             # filter tests
+    cargo spectacular --package my-crate       # specific package
+    cargo spectacular --exclude-tag slow       # fast run, skip slow tests
+    cargo spectacular --tag slow --tag db      # only slow or db tests
+    cargo spectacular --shard 3/8              # the third of eight CI jobs
+    cargo spectacular list --tag slow          # which tests are tagged slow
+    cargo spectacular list --json              # for editors and other tools
+    cargo spectacular --junit target/junit.xml # terminal output plus JUnit report
+    cargo spectacular --format exec:./fmt.py   # hand events to your own formatter
+    cargo spectacular --watch                  # re-run on save; f re-runs failures
+    cargo spectacular --only-failures          # just what failed last time
+    cargo spectacular --profile 5              # the 5 slowest tests and groups
+    cargo spectacular --fail-on-slowdown       # CI: fail when tests get slower
+    cargo spectacular -v                       # also cargo's output and unknown events
+    cargo spectacular --backtrace              # where in your code each failure came from
+    cargo spectacular --fail-fast              # stop at the first failure
+    cargo spectacular --color always           # keep color in CI logs
+    cargo spectacular --theme colorblind       # colors that stay apart with color blindness
+    cargo spectacular --save-baseline main     # measure the benchmarks and keep them
+    cargo spectacular --baseline main          # compare the benchmarks with main"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_values_split_on_commas() {
+        let tags: Vec<String> = split_tags("slow, db,,").collect();
+        assert_eq!(tags, ["slow", "db"]);
+        assert_eq!(split_tags("").count(), 0);
+    }
+}
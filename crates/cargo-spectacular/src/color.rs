@@ -0,0 +1,319 @@
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::io::{self, IsTerminal, Write};
+
+/// When to color the output (`--color`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Color terminals, as `NO_COLOR` and `CLICOLOR_FORCE` allow.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    pub fn parse(name: &str) -> Option<ColorMode> {
+        match name {
+            "auto" => Some(ColorMode::Auto),
+            "always" => Some(ColorMode::Always),
+            "never" => Some(ColorMode::Never),
+            _ => None,
+        }
+    }
+
+    /// Whether output to stdout is colored.
+    pub fn stdout(self) -> bool {
+        self.enabled(io::stdout().is_terminal(), &Env::read())
+    }
+
+    /// Whether output to stderr is colored.
+    pub fn stderr(self) -> bool {
+        self.enabled(io::stderr().is_terminal(), &Env::read())
+    }
+
+    /// In auto mode a non-empty `NO_COLOR` turns color off, and a
+    /// `CLICOLOR_FORCE` other than `0` turns it on where it isn't a terminal.
+    fn enabled(self, terminal: bool, env: &Env) -> bool {
+        match self {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => {
+                if env.no_color.as_ref().is_some_and(|v| !v.is_empty()) {
+                    false
+                } else {
+                    terminal || env.clicolor_force.as_ref().is_some_and(|v| v != "0")
+                }
+            }
+        }
+    }
+}
+
+/// The environment variables that say how to color.
+#[derive(Debug, Default)]
+struct Env {
+    no_color: Option<OsString>,
+    clicolor_force: Option<OsString>,
+    colorterm: Option<OsString>,
+    term: Option<OsString>,
+    wt_session: Option<OsString>,
+}
+
+impl Env {
+    fn read() -> Env {
+        Env {
+            no_color: std::env::var_os("NO_COLOR"),
+            clicolor_force: std::env::var_os("CLICOLOR_FORCE"),
+            colorterm: std::env::var_os("COLORTERM"),
+            term: std::env::var_os("TERM"),
+            wt_session: std::env::var_os("WT_SESSION"),
+        }
+    }
+}
+
+/// How many colors the terminal shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Depth {
+    /// 24-bit RGB.
+    TrueColor,
+    /// The xterm 256-color palette.
+    Ansi256,
+    /// The 16 basic ANSI colors, which CI logs render too.
+    Ansi16,
+}
+
+impl Depth {
+    /// What the terminal advertises: `COLORTERM=truecolor` or `24bit`, then
+    /// a `TERM` containing `256color`. Anything else gets the basic colors.
+    fn detect() -> Depth {
+        Depth::from_env(&Env::read())
+    }
+
+    fn from_env(env: &Env) -> Depth {
+        let colorterm = env.colorterm.as_deref().unwrap_or_default();
+        let term = env.term.as_deref().unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" || env.wt_session.is_some() {
+            Depth::TrueColor
+        } else if term.to_string_lossy().contains("256color") {
+            Depth::Ansi256
+        } else {
+            Depth::Ansi16
+        }
+    }
+}
+
+/// What a color marks; the [`Theme`] decides which color that is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// Passing tests.
+    Success,
+    /// Failing tests, and tests that may have hung.
+    Failure,
+    /// Ignored tests, and slow ones.
+    Warning,
+}
+
+/// The palette for the roles (`--theme`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Green, red and yellow, for dark backgrounds.
+    #[default]
+    Default,
+    /// Okabe–Ito sky blue, vermillion and yellow, which stay apart with
+    /// red–green color blindness.
+    Colorblind,
+    /// Darker green, red and amber, for light backgrounds.
+    Light,
+}
+
+impl Theme {
+    pub fn parse(name: &str) -> Option<Theme> {
+        match name {
+            "default" => Some(Theme::Default),
+            "colorblind" => Some(Theme::Colorblind),
+            "light" => Some(Theme::Light),
+            _ => None,
+        }
+    }
+
+    pub fn rgb(self, role: Role) -> (u8, u8, u8) {
+        match (self, role) {
+            (Theme::Default, Role::Success) => (100, 200, 120),
+            (Theme::Default, Role::Failure) => (210, 90, 90),
+            (Theme::Default, Role::Warning) => (200, 180, 80),
+            (Theme::Colorblind, Role::Success) => (86, 180, 233),
+            (Theme::Colorblind, Role::Failure) => (213, 94, 0),
+            (Theme::Colorblind, Role::Warning) => (240, 228, 66),
+            (Theme::Light, Role::Success) => (0, 135, 60),
+            (Theme::Light, Role::Failure) => (190, 30, 30),
+            (Theme::Light, Role::Warning) => (160, 110, 0),
+        }
+    }
+}
+
+/// The colors output is written in: the theme's, at the depth the terminal
+/// shows.
+///
+/// Formatters that color their output get one; `None` in its place means
+/// plain text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    theme: Theme,
+    depth: Depth,
+}
+
+impl Palette {
+    /// `theme` at the depth the terminal advertises.
+    pub fn new(theme: Theme) -> Palette {
+        Palette::with_depth(theme, Depth::detect())
+    }
+
+    pub(crate) const fn with_depth(theme: Theme, depth: Depth) -> Palette {
+        Palette { theme, depth }
+    }
+
+    /// Start writing in the theme's color for `role`.
+    pub fn paint(self, w: &mut dyn Write, role: Role) -> io::Result<()> {
+        let (r, g, b) = self.theme.rgb(role);
+        self.fg(w, r, g, b)
+    }
+
+    /// Start writing in the color nearest to `r`, `g`, `b` the terminal shows.
+    pub fn fg(self, w: &mut dyn Write, r: u8, g: u8, b: u8) -> io::Result<()> {
+        match self.depth {
+            Depth::TrueColor => write!(w, "\x1b[38;2;{r};{g};{b}m"),
+            Depth::Ansi256 => write!(w, "\x1b[38;5;{}m", ansi256(r, g, b)),
+            Depth::Ansi16 => write!(w, "\x1b[{}m", ansi16(r, g, b)),
+        }
+    }
+}
+
+/// Go back to the terminal's own color.
+pub fn reset(w: &mut dyn Write) -> io::Result<()> {
+    write!(w, "\x1b[0m")
+}
+
+/// The nearest color of the xterm palette: its gray ramp, or its 6×6×6 cube.
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    if r == g && g == b {
+        return match r {
+            0..8 => 16,
+            249.. => 231,
+            _ => 232 + ((r - 8) / 10).min(23),
+        };
+    }
+    let level = |c: u8| (c as u16 * 5 + 127) / 255;
+    (16 + 36 * level(r) + 6 * level(g) + level(b)) as u8
+}
+
+/// The basic color with the same hue: the channels near the strongest one
+/// are switched on, and bright colors get the bright variant.
+fn ansi16(r: u8, g: u8, b: u8) -> u8 {
+    let max = r.max(g).max(b);
+    if max < 64 {
+        return 30;
+    }
+    let on = |c: u8| (c as u16 * 100 >= max as u16 * 65) as u8;
+    let base = on(r) | on(g) << 1 | on(b) << 2;
+    if max >= 192 { 90 + base } else { 30 + base }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(vars: &[(&str, &str)]) -> Env {
+        let get = |key: &str| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| OsString::from(v))
+        };
+        Env {
+            no_color: get("NO_COLOR"),
+            clicolor_force: get("CLICOLOR_FORCE"),
+            colorterm: get("COLORTERM"),
+            term: get("TERM"),
+            wt_session: get("WT_SESSION"),
+        }
+    }
+
+    #[test]
+    fn auto_follows_the_terminal_no_color_then_clicolor_force() {
+        let auto = |terminal, vars| ColorMode::Auto.enabled(terminal, &env(vars));
+        assert!(auto(true, &[]));
+        assert!(!auto(false, &[]));
+        assert!(!auto(true, &[("NO_COLOR", "1")]));
+        // An empty NO_COLOR doesn't count
+        assert!(auto(true, &[("NO_COLOR", "")]));
+        assert!(auto(false, &[("CLICOLOR_FORCE", "1")]));
+        assert!(!auto(false, &[("CLICOLOR_FORCE", "0")]));
+        // NO_COLOR wins over CLICOLOR_FORCE
+        assert!(!auto(false, &[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]));
+    }
+
+    #[test]
+    fn always_and_never_ignore_the_environment() {
+        assert!(ColorMode::Always.enabled(false, &env(&[("NO_COLOR", "1")])));
+        assert!(!ColorMode::Never.enabled(true, &env(&[("CLICOLOR_FORCE", "1")])));
+    }
+
+    #[test]
+    fn depth_from_the_terminal() {
+        let depth = |vars| Depth::from_env(&env(vars));
+        assert_eq!(depth(&[("COLORTERM", "truecolor")]), Depth::TrueColor);
+        assert_eq!(depth(&[("COLORTERM", "24bit")]), Depth::TrueColor);
+        assert_eq!(depth(&[("WT_SESSION", "1")]), Depth::TrueColor);
+        assert_eq!(depth(&[("TERM", "xterm-256color")]), Depth::Ansi256);
+        assert_eq!(depth(&[("TERM", "xterm")]), Depth::Ansi16);
+        assert_eq!(depth(&[]), Depth::Ansi16);
+    }
+
+    #[test]
+    fn ansi256_picks_the_gray_ramp_or_the_cube() {
+        assert_eq!(ansi256(0, 0, 0), 16);
+        assert_eq!(ansi256(255, 255, 255), 231);
+        assert_eq!(ansi256(8, 8, 8), 232);
+        assert_eq!(ansi256(128, 128, 128), 244);
+        assert_eq!(ansi256(238, 238, 238), 255);
+        assert_eq!(ansi256(255, 0, 0), 196);
+        assert_eq!(ansi256(0, 255, 0), 46);
+        assert_eq!(ansi256(0, 0, 255), 21);
+        // The default theme's green
+        assert_eq!(ansi256(100, 200, 120), 114);
+    }
+
+    #[test]
+    fn ansi16_keeps_the_hue() {
+        assert_eq!(ansi16(10, 20, 30), 30);
+        assert_eq!(ansi16(255, 0, 0), 91);
+        assert_eq!(ansi16(150, 0, 0), 31);
+        assert_eq!(ansi16(100, 200, 120), 92);
+        assert_eq!(ansi16(210, 90, 90), 91);
+        assert_eq!(ansi16(200, 180, 80), 93);
+        assert_eq!(ansi16(86, 180, 233), 96);
+        assert_eq!(ansi16(255, 255, 255), 97);
+    }
+
+    #[test]
+    fn palettes_write_the_theme_at_their_depth() {
+        let paint = |theme, depth| {
+            let mut out = Vec::new();
+            Palette::with_depth(theme, depth)
+                .paint(&mut out, Role::Failure)
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(
+            paint(Theme::Default, Depth::TrueColor),
+            "\x1b[38;2;210;90;90m"
+        );
+        assert_eq!(
+            paint(Theme::Colorblind, Depth::TrueColor),
+            "\x1b[38;2;213;94;0m"
+        );
+        assert_eq!(paint(Theme::Light, Depth::Ansi256), "\x1b[38;5;167m");
+        assert_eq!(paint(Theme::Default, Depth::Ansi16), "\x1b[91m");
+    }
+}
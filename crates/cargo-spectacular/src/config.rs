@@ -0,0 +1,319 @@
+use crate::artifacts::{Package, Workspace};
+use crate::color::{ColorMode, Theme};
+use serde::Deserialize;
+use std::io;
+use std::path::Path;
+
+/// File name of the standalone configuration, at the workspace root.
+const FILE_NAME: &str = "Spectacular.toml";
+
+/// Runner defaults a project can check in, so every machine runs the same way.
+///
+/// Read from `[workspace.metadata.spectacular]`, then `Spectacular.toml` at
+/// the workspace root, then the selected package's
+/// `[package.metadata.spectacular]`; each overrides the keys it sets.
+/// Command-line flags override them all.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Settings {
+    pub format: Option<String>,
+    /// Arguments for the test binaries, used when none follow `--`.
+    pub test_args: Option<Vec<String>>,
+    /// Also write a JUnit XML report here.
+    pub junit: Option<String>,
+    /// Tags to skip, used when no `--exclude-tag` is given, less any `--tag` names.
+    pub exclude_tags: Option<Vec<String>>,
+    /// How many of the slowest tests and groups to list after each run.
+    pub profile: Option<usize>,
+    pub color: Option<ColorMode>,
+    pub theme: Option<Theme>,
+}
+
+impl Settings {
+    /// The configured tags to skip in a run that asks for `tags`: a tag the
+    /// run asks for is run rather than skipped.
+    pub fn exclude_tags_besides(&self, tags: &[String]) -> Vec<String> {
+        let excluded = self.exclude_tags.iter().flatten();
+        excluded.filter(|t| !tags.contains(t)).cloned().collect()
+    }
+
+    /// Layer `other` on top: its keys win where it sets them.
+    fn merge(self, other: Settings) -> Settings {
+        Settings {
+            format: other.format.or(self.format),
+            test_args: other.test_args.or(self.test_args),
+            junit: other.junit.or(self.junit),
+            exclude_tags: other.exclude_tags.or(self.exclude_tags),
+            profile: other.profile.or(self.profile),
+            color: other.color.or(self.color),
+            theme: other.theme.or(self.theme),
+        }
+    }
+}
+
+/// Read the settings for the workspace and package a run selects.
+pub fn load(
+    workspace: &Workspace,
+    package: Option<&str>,
+    manifest_path: Option<&str>,
+) -> io::Result<Settings> {
+    let mut settings = from_metadata(&workspace.metadata, "[workspace.metadata.spectacular]")?;
+
+    let path = workspace.workspace_root.join(FILE_NAME);
+    match std::fs::read_to_string(&path) {
+        Ok(text) => {
+            let file = toml::from_str(&text).map_err(|e| invalid(&path.display(), e))?;
+            settings = settings.merge(file);
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+
+    if let Some(package) = selected_package(workspace, package, manifest_path) {
+        let table = format!("[package.metadata.spectacular] of {}", package.name);
+        settings = settings.merge(from_metadata(&package.metadata, &table)?);
+    }
+    Ok(settings)
+}
+
+fn from_metadata(metadata: &serde_json::Value, source: &str) -> io::Result<Settings> {
+    match metadata.get("spectacular") {
+        Some(table) => Settings::deserialize(table).map_err(|e| invalid(&source, e)),
+        None => Ok(Settings::default()),
+    }
+}
+
+/// The package a run is about: the `--package`, else the one whose manifest
+/// `--manifest-path` names, else the innermost one holding the current directory.
+fn selected_package<'a>(
+    workspace: &'a Workspace,
+    name: Option<&str>,
+    manifest_path: Option<&str>,
+) -> Option<&'a Package> {
+    if let Some(name) = name {
+        return workspace.packages.iter().find(|p| p.name == name);
+    }
+    if let Some(path) = manifest_path {
+        let path = Path::new(path).canonicalize().ok()?;
+        return workspace.packages.iter().find(|p| p.manifest_path == path);
+    }
+    let cwd = std::env::current_dir().ok()?;
+    workspace
+        .packages
+        .iter()
+        .filter_map(|p| Some((p, p.manifest_path.parent()?)))
+        .filter(|(_, dir)| cwd.starts_with(dir))
+        .max_by_key(|(_, dir)| dir.as_os_str().len())
+        .map(|(p, _)| p)
+}
+
+fn invalid(source: &dyn std::fmt::Display, e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{source}: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::scratch;
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    fn package(name: &str, dir: &Path, metadata: serde_json::Value) -> Package {
+        Package {
+            name: name.to_string(),
+            manifest_path: dir.join("Cargo.toml"),
+            metadata,
+        }
+    }
+
+    /// A workspace in a fresh directory, with `file` as its `Spectacular.toml`.
+    fn workspace(
+        test: &str,
+        metadata: serde_json::Value,
+        file: Option<&str>,
+        packages: impl FnOnce(&Path) -> Vec<Package>,
+    ) -> Workspace {
+        let root = scratch(&format!("config-{test}")).canonicalize().unwrap();
+        if let Some(text) = file {
+            std::fs::write(root.join(FILE_NAME), text).unwrap();
+        }
+        Workspace {
+            packages: packages(&root),
+            target_directory: root.join("target"),
+            workspace_root: root,
+            metadata,
+        }
+    }
+
+    fn cleanup(workspace: Workspace) {
+        std::fs::remove_dir_all(workspace.workspace_root).unwrap();
+    }
+
+    #[test]
+    fn later_layers_override_the_keys_they_set() {
+        let ws = workspace(
+            "layers",
+            json!({"spectacular": {"format": "progress", "profile": 5, "junit": "ws.xml"}}),
+            Some("format = \"documentation\"\ntheme = \"light\"\n"),
+            |root| {
+                vec![package(
+                    "auth",
+                    &root.join("auth"),
+                    json!({"spectacular": {"theme": "colorblind"}}),
+                )]
+            },
+        );
+        let settings = load(&ws, Some("auth"), None);
+        cleanup(ws);
+
+        let settings = settings.unwrap();
+        // Spectacular.toml over the workspace table
+        assert_eq!(settings.format.as_deref(), Some("documentation"));
+        // The package table over both
+        assert_eq!(settings.theme, Some(Theme::Colorblind));
+        // Untouched by the later layers
+        assert_eq!(settings.profile, Some(5));
+        assert_eq!(settings.junit.as_deref(), Some("ws.xml"));
+        assert_eq!(settings.color, None);
+    }
+
+    #[test]
+    fn a_package_setting_some_keys_keeps_the_rest() {
+        let ws = workspace(
+            "partial",
+            json!({"spectacular": {
+                "test-args": ["--test-threads", "2"],
+                "exclude-tags": ["slow"],
+            }}),
+            None,
+            |root| {
+                vec![
+                    package(
+                        "auth",
+                        &root.join("auth"),
+                        json!({"spectacular": {"exclude-tags": []}}),
+                    ),
+                    package(
+                        "billing",
+                        &root.join("billing"),
+                        json!({"spectacular": {"format": "tap"}}),
+                    ),
+                ]
+            },
+        );
+        let auth = load(&ws, Some("auth"), None);
+        let other = load(&ws, Some("billing"), None);
+        cleanup(ws);
+
+        let auth = auth.unwrap();
+        assert_eq!(auth.exclude_tags, Some(Vec::new()));
+        assert_eq!(auth.test_args.unwrap(), ["--test-threads", "2"]);
+        assert_eq!(auth.format, None);
+        // Another package's table doesn't apply
+        let billing = other.unwrap();
+        assert_eq!(billing.format.as_deref(), Some("tap"));
+        assert_eq!(billing.exclude_tags.unwrap(), ["slow"]);
+    }
+
+    #[test]
+    fn tags_asked_for_are_not_excluded() {
+        let ws = workspace(
+            "tags",
+            json!({}),
+            Some("exclude-tags = [\"slow\", \"db\"]\n"),
+            |_| Vec::new(),
+        );
+        let settings = load(&ws, None, None);
+        cleanup(ws);
+
+        let settings = settings.unwrap();
+        assert_eq!(settings.exclude_tags_besides(&[]), ["slow", "db"]);
+        assert_eq!(
+            settings.exclude_tags_besides(&[String::from("slow")]),
+            ["db"]
+        );
+        assert!(Settings::default().exclude_tags_besides(&[]).is_empty());
+    }
+
+    #[test]
+    fn unknown_keys_name_their_source() {
+        let ws = workspace("unknown", json!({}), Some("formatt = \"tap\"\n"), |_| {
+            Vec::new()
+        });
+        let err = load(&ws, None, None).unwrap_err();
+        let path = ws.workspace_root.join(FILE_NAME);
+        cleanup(ws);
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let message = err.to_string();
+        assert!(
+            message.starts_with(&format!("{}: ", path.display())),
+            "{message}"
+        );
+        assert!(message.contains("unknown field `formatt`"), "{message}");
+        assert!(message.contains("expected one of `format`"), "{message}");
+    }
+
+    #[test]
+    fn bad_package_values_name_the_package() {
+        let ws = workspace("package-error", json!({}), None, |root| {
+            vec![package(
+                "auth",
+                root,
+                json!({"spectacular": {"profile": "ten"}}),
+            )]
+        });
+        let err = load(&ws, Some("auth"), None).unwrap_err();
+        cleanup(ws);
+
+        let message = err.to_string();
+        assert!(
+            message.starts_with("[package.metadata.spectacular] of auth: "),
+            "{message}"
+        );
+        assert!(message.contains("invalid type"), "{message}");
+    }
+
+    #[test]
+    fn selected_package_prefers_the_name_then_the_manifest() {
+        let ws = workspace("select", json!({}), None, |root| {
+            let auth = root.join("auth");
+            std::fs::create_dir_all(&auth).unwrap();
+            std::fs::write(auth.join("Cargo.toml"), "").unwrap();
+            vec![
+                package("root", root, json!({})),
+                package("auth", &auth, json!({})),
+            ]
+        });
+        let manifest = ws.packages[1].manifest_path.display().to_string();
+
+        let name = |p: &Package| p.name.clone();
+        let by_name = selected_package(&ws, Some("root"), Some(&manifest)).map(name);
+        let by_manifest = selected_package(&ws, None, Some(&manifest)).map(name);
+        let unknown = selected_package(&ws, Some("nope"), None).map(name);
+        cleanup(ws);
+
+        assert_eq!(by_name.as_deref(), Some("root"));
+        assert_eq!(by_manifest.as_deref(), Some("auth"));
+        assert_eq!(unknown, None);
+    }
+
+    #[test]
+    fn selected_package_defaults_to_the_innermost_around_the_cwd() {
+        // Tests run from this package's directory, inside the workspace root
+        let here = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let root = here.parent().unwrap().parent().unwrap();
+        let ws = Workspace {
+            workspace_root: root.to_path_buf(),
+            target_directory: root.join("target"),
+            packages: vec![
+                package("root", root, json!({})),
+                package("this", &here, json!({})),
+                package("sibling", &root.join("crates/elsewhere"), json!({})),
+            ],
+            metadata: json!({}),
+        };
+        let selected = selected_package(&ws, None, None).map(|p| p.name.as_str());
+        assert_eq!(selected, Some("this"));
+    }
+}
@@ -0,0 +1,286 @@
+use serde::Deserialize;
+use std::io::{self, IsTerminal, Write};
+
+/// A rustc diagnostic from a `compiler-message` line of cargo's JSON output.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Diagnostic {
+    pub message: String,
+    pub level: String,
+    #[serde(default)]
+    pub code: Option<Code>,
+    #[serde(default)]
+    pub spans: Vec<Span>,
+    /// The diagnostic as rustc would print it.
+    #[serde(default)]
+    pub rendered: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Code {
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Span {
+    pub file_name: String,
+    pub line_start: u32,
+    pub column_start: u32,
+    pub is_primary: bool,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.level.starts_with("error") && !self.is_tally()
+    }
+
+    pub fn is_warning(&self) -> bool {
+        self.level == "warning" && !self.is_tally()
+    }
+
+    /// rustc's closing counts ("aborting due to ...", "2 warnings emitted").
+    fn is_tally(&self) -> bool {
+        self.spans.is_empty()
+            && (self.message.starts_with("aborting due to") || self.message.ends_with(" emitted"))
+    }
+
+    /// Two lines: `error[E0308]: message`, then where, with the primary label.
+    pub fn write_compact(&self, color: bool, w: &mut dyn Write) -> io::Result<()> {
+        let level = match self.code {
+            Some(ref code) => format!("{}[{}]", self.level, code.code),
+            None => self.level.clone(),
+        };
+        if color {
+            let ansi = if self.level.starts_with("error") {
+                "31"
+            } else {
+                "33"
+            };
+            writeln!(w, "\x1b[1;{ansi}m{level}\x1b[0m: {}", self.message)?;
+        } else {
+            writeln!(w, "{level}: {}", self.message)?;
+        }
+
+        if let Some(span) = self.spans.iter().find(|s| s.is_primary) {
+            write!(
+                w,
+                "  at {}:{}:{}",
+                span.file_name, span.line_start, span.column_start
+            )?;
+            if let Some(ref label) = span.label {
+                write!(w, ": {label}")?;
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+
+    /// The rendered form, or the compact one when rustc sent none.
+    pub fn write_rendered(&self, color: bool, w: &mut dyn Write) -> io::Result<()> {
+        match self.rendered {
+            Some(ref rendered) => write!(w, "{rendered}"),
+            None => self.write_compact(color, w),
+        }
+    }
+
+    /// Identity for de-duplication: the same diagnostic is reported once per target.
+    pub fn key(&self) -> String {
+        match self.rendered {
+            Some(ref rendered) => rendered.clone(),
+            None => format!("{}: {}", self.level, self.message),
+        }
+    }
+}
+
+/// Frames for the build progress line.
+const FRAMES: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// One self-overwriting progress line on stderr while cargo builds.
+///
+/// It moves each time cargo reports progress. When stderr isn't a terminal
+/// it stays silent.
+pub struct Spinner {
+    enabled: bool,
+    frame: usize,
+    compiled: usize,
+    current: String,
+}
+
+impl Spinner {
+    pub fn new() -> Self {
+        Self {
+            enabled: io::stderr().is_terminal(),
+            frame: 0,
+            compiled: 0,
+            current: String::new(),
+        }
+    }
+
+    /// A cargo status line, such as `Compiling foo v0.1.0 (...)`.
+    pub fn status(&mut self, line: &str) {
+        let mut words = line.split_whitespace();
+        if let (Some(verb), Some(name)) = (words.next(), words.next()) {
+            self.current = format!("{verb} {name}");
+            self.draw();
+        }
+    }
+
+    /// A crate finished compiling.
+    pub fn artifact(&mut self) {
+        self.compiled += 1;
+        self.draw();
+    }
+
+    fn draw(&mut self) {
+        if !self.enabled {
+            return;
+        }
+        let frame = FRAMES[self.frame % FRAMES.len()];
+        self.frame += 1;
+        let mut stderr = io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r\x1b[2K{frame} Building tests: {} compiled  {}",
+            self.compiled, self.current
+        );
+        let _ = stderr.flush();
+    }
+
+    /// Erase the progress line.
+    pub fn clear(&mut self) {
+        if self.enabled {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(stderr, "\r\x1b[2K");
+            let _ = stderr.flush();
+        }
+    }
+}
+
+/// Whether `line` is one of cargo's right-aligned status lines (`   Compiling foo`).
+pub fn is_status_line(line: &str) -> bool {
+    let line = strip_ansi(line);
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+    let verb = trimmed.split(' ').next().unwrap_or_default();
+    indent > 0
+        && verb.starts_with(|c: char| c.is_ascii_uppercase())
+        && verb.chars().all(|c| c.is_ascii_alphabetic() || c == '-')
+        && indent + verb.len() == 12
+}
+
+/// Whether `line` is part of cargo's note on failed test targets: `error: test
+/// failed, to rerun pass ...`, or `error: N targets failed:` and the indented
+/// targets listed under it.
+pub fn is_rerun_hint(line: &str) -> bool {
+    let line = strip_ansi(line);
+    if let Some(message) = line.strip_prefix("error: ") {
+        return message.starts_with("test failed, to rerun pass ")
+            || message.ends_with(" target failed:")
+            || message.ends_with(" targets failed:");
+    }
+    let target = line.trim_start();
+    target.len() < line.len() && target.starts_with('`') && target.trim_end().ends_with('`')
+}
+
+/// Remove ANSI color sequences (`ESC [ ... m`) from cargo's colored output.
+pub fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(json: &str) -> Diagnostic {
+        serde_json::from_str(json).unwrap()
+    }
+
+    const MISMATCH: &str = r#"{
+        "message": "mismatched types",
+        "level": "error",
+        "code": {"code": "E0308"},
+        "spans": [
+            {"file_name": "src/lib.rs", "line_start": 1, "column_start": 5, "is_primary": false},
+            {"file_name": "src/lib.rs", "line_start": 3, "column_start": 9, "is_primary": true,
+             "label": "expected `u32`, found `&str`"}
+        ]
+    }"#;
+
+    #[test]
+    fn compact_names_the_code_and_the_primary_span() {
+        let mut out = Vec::new();
+        diagnostic(MISMATCH).write_compact(false, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "error[E0308]: mismatched types\n  at src/lib.rs:3:9: expected `u32`, found `&str`\n"
+        );
+    }
+
+    #[test]
+    fn rendered_falls_back_to_compact() {
+        let mut out = Vec::new();
+        let unused = diagnostic(r#"{"message": "unused variable", "level": "warning"}"#);
+        unused.write_rendered(false, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "warning: unused variable\n"
+        );
+        assert_eq!(unused.key(), "warning: unused variable");
+    }
+
+    #[test]
+    fn tallies_are_neither_errors_nor_warnings() {
+        assert!(diagnostic(MISMATCH).is_error());
+        let aborting =
+            diagnostic(r#"{"message": "aborting due to 1 previous error", "level": "error"}"#);
+        let emitted = diagnostic(r#"{"message": "2 warnings emitted", "level": "warning"}"#);
+        assert!(!aborting.is_error());
+        assert!(!emitted.is_warning());
+    }
+
+    #[test]
+    fn status_lines_are_right_aligned_verbs() {
+        assert!(is_status_line("   Compiling foo v0.1.0 (/work)"));
+        assert!(is_status_line(
+            "    Finished `test` profile [unoptimized] target(s)"
+        ));
+        assert!(is_status_line(
+            "\x1b[1m\x1b[32m     Running\x1b[0m tests/auth.rs"
+        ));
+        assert!(!is_status_line("Compiling foo"));
+        assert!(!is_status_line("  running 3 tests"));
+        assert!(!is_status_line("test auth::login ... ok"));
+    }
+
+    #[test]
+    fn rerun_hints() {
+        assert!(is_rerun_hint(
+            "\x1b[1m\x1b[91merror\x1b[0m: test failed, to rerun pass `--test auth`"
+        ));
+        assert!(is_rerun_hint("error: 2 targets failed:"));
+        assert!(is_rerun_hint("error: 1 target failed:"));
+        assert!(is_rerun_hint("    `-p demo --test auth`"));
+        assert!(!is_rerun_hint("error: could not compile `demo`"));
+        assert!(!is_rerun_hint("`--test auth`"));
+    }
+
+    #[test]
+    fn strip_ansi_keeps_the_text() {
+        assert_eq!(strip_ansi("\x1b[1;31merror\x1b[0m: boom"), "error: boom");
+        assert_eq!(strip_ansi("plain"), "plain");
+    }
+}
@@ -1,21 +1,33 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 /// Top-level libtest JSON event, discriminated by `"type"`.
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type")]
+#[non_exhaustive]
 pub enum Event {
     #[serde(rename = "suite")]
     Suite(SuiteEvent),
     #[serde(rename = "test")]
     Test(TestEvent),
+    #[serde(rename = "bench")]
+    Bench(BenchResult),
+    /// Timings of rustdoc's merged doc-tests, sent after their suite.
+    #[serde(rename = "report")]
+    Report(DocTestTimes),
 }
 
 /// Suite-level events: started, ok, failed.
 #[derive(Debug, Deserialize)]
 #[serde(tag = "event")]
+#[non_exhaustive]
 pub enum SuiteEvent {
     #[serde(rename = "started")]
-    Started { test_count: usize },
+    Started {
+        test_count: usize,
+        /// The seed the tests were shuffled with, under `--shuffle`.
+        #[serde(default)]
+        shuffle_seed: Option<u64>,
+    },
     #[serde(rename = "ok")]
     Ok(SuiteResult),
     #[serde(rename = "failed")]
@@ -23,18 +35,26 @@ pub enum SuiteEvent {
 }
 
 /// Aggregated suite results emitted at the end.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct SuiteResult {
     pub passed: usize,
     pub failed: usize,
     pub ignored: usize,
+    /// Benchmarks that ran.
+    #[serde(default)]
+    pub measured: usize,
+    /// Tests left out by the name filters.
+    #[serde(default)]
+    pub filtered_out: usize,
     #[serde(default)]
     pub exec_time: Option<f64>,
 }
 
-/// Individual test events: started, ok, failed, ignored.
+/// Individual test events: started, ok, failed, ignored, allowed_fail, timeout.
 #[derive(Debug, Deserialize)]
 #[serde(tag = "event")]
+#[non_exhaustive]
 pub enum TestEvent {
     #[serde(rename = "started")]
     Started { name: String },
@@ -43,6 +63,9 @@ pub enum TestEvent {
         name: String,
         #[serde(default)]
         exec_time: Option<f64>,
+        /// Captured output of a passing test, under `--show-output`.
+        #[serde(default)]
+        stdout: Option<String>,
     },
     #[serde(rename = "failed")]
     Failed {
@@ -53,7 +76,95 @@ pub enum TestEvent {
         stdout: Option<String>,
         #[serde(default)]
         message: Option<String>,
+        /// Why libtest failed a test that didn't panic, e.g. "time limit exceeded".
+        #[serde(default)]
+        reason: Option<String>,
     },
     #[serde(rename = "ignored")]
-    Ignored { name: String },
+    Ignored {
+        name: String,
+        /// The reason given in `#[ignore = "..."]`.
+        #[serde(default)]
+        message: Option<String>,
+    },
+    /// Failed, but marked as allowed to; it doesn't fail the suite.
+    #[serde(rename = "allowed_fail")]
+    AllowedFail { name: String },
+    /// Still running after libtest's warning time (60 seconds); the test carries on.
+    #[serde(rename = "timeout")]
+    Timeout { name: String },
+}
+
+/// One benchmark's measurement, in nanoseconds per iteration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct BenchResult {
+    pub name: String,
+    pub median: f64,
+    pub deviation: f64,
+    /// Throughput, for benchmarks that set the bytes they process.
+    #[serde(default)]
+    pub mib_per_second: Option<u64>,
+}
+
+/// How long rustdoc took to compile and run merged doc-tests, in seconds.
+#[derive(Debug, Clone, Deserialize)]
+#[non_exhaustive]
+pub struct DocTestTimes {
+    pub total_time: f64,
+    pub compilation_time: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(json: &str) -> Event {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn reads_libtest_events() {
+        assert!(matches!(
+            event(r#"{ "type": "suite", "event": "started", "test_count": 3, "shuffle_seed": 7 }"#),
+            Event::Suite(SuiteEvent::Started {
+                test_count: 3,
+                shuffle_seed: Some(7)
+            })
+        ));
+        assert!(matches!(
+            event(r#"{ "type": "test", "event": "ok", "name": "a::b", "exec_time": 0.5 }"#),
+            Event::Test(TestEvent::Ok { ref name, exec_time: Some(0.5), stdout: None }) if name == "a::b"
+        ));
+        assert!(matches!(
+            event(r#"{ "type": "test", "name": "slow", "event": "ignored", "message": "flaky" }"#),
+            Event::Test(TestEvent::Ignored { message: Some(ref m), .. }) if m == "flaky"
+        ));
+        assert!(matches!(
+            event(r#"{ "type": "report", "total_time": 1.5, "compilation_time": 1.0 }"#),
+            Event::Report(DocTestTimes {
+                total_time: 1.5,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn suite_results_default_what_older_libtests_leave_out() {
+        let Event::Suite(SuiteEvent::Failed(result)) = event(
+            r#"{ "type": "suite", "event": "failed", "passed": 2, "failed": 1, "ignored": 0 }"#,
+        ) else {
+            panic!("not a failed suite");
+        };
+        assert_eq!((result.passed, result.failed, result.measured), (2, 1, 0));
+        assert_eq!(result.exec_time, None);
+    }
+
+    #[test]
+    fn unknown_events_are_errors() {
+        assert!(
+            serde_json::from_str::<Event>(r#"{ "type": "test", "event": "paused", "name": "a" }"#)
+                .is_err()
+        );
+    }
 }
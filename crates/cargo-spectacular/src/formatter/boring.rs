@@ -1,5 +1,11 @@
-use super::{FailedTest, Formatter, write_colored_failures, write_colored_summary};
+use super::{
+    FailedTest, Formatter, write_breakdown, write_colored_summary, write_failures, write_not_run,
+    write_section, write_shard, write_shuffle_seed,
+};
+use crate::color::Palette;
 use crate::event::SuiteResult;
+use crate::meta::Catalog;
+use crate::summary::RunSummary;
 use crossterm::terminal;
 use std::io::{self, Write};
 
@@ -7,15 +13,21 @@ pub struct BoringFormatter {
     dot_count: usize,
     cols: u16,
     failures: Vec<FailedTest>,
+    catalog: Catalog,
+    binary: Option<String>,
+    palette: Palette,
 }
 
 impl BoringFormatter {
-    pub fn new() -> Self {
+    pub fn new(palette: Palette) -> Self {
         let cols = terminal::size().map(|(w, _)| w).unwrap_or(80);
         Self {
             dot_count: 0,
             cols,
             failures: Vec::new(),
+            catalog: Catalog::default(),
+            binary: None,
+            palette,
         }
     }
 
@@ -31,11 +43,24 @@ impl BoringFormatter {
 }
 
 impl Formatter for BoringFormatter {
-    fn suite_started(&mut self, test_count: usize, w: &mut dyn Write) -> io::Result<()> {
-        writeln!(w, "\nRunning {test_count} tests\n")?;
+    fn metadata(&mut self, catalog: &Catalog) {
+        self.catalog = catalog.clone();
+    }
+
+    fn binary_started(&mut self, label: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.binary = Some(label.to_string());
         Ok(())
     }
 
+    fn suite_started(&mut self, test_count: usize, w: &mut dyn Write) -> io::Result<()> {
+        self.dot_count = 0;
+        write_section(self.binary.as_deref(), test_count, w)
+    }
+
+    fn suite_shuffled(&mut self, seed: u64, w: &mut dyn Write) -> io::Result<()> {
+        write_shuffle_seed(seed, w)
+    }
+
     fn test_started(&mut self, _name: &str, _w: &mut dyn Write) -> io::Result<()> {
         Ok(())
     }
@@ -57,12 +82,14 @@ impl Formatter for BoringFormatter {
         message: Option<&str>,
         w: &mut dyn Write,
     ) -> io::Result<()> {
-        self.failures.push(FailedTest {
-            name: name.to_string(),
+        self.failures.push(FailedTest::new(
+            self.binary.as_deref(),
+            name,
             exec_time,
-            stdout: stdout.map(String::from),
-            message: message.map(String::from),
-        });
+            stdout,
+            message,
+            &self.catalog,
+        ));
         self.emit_dot('X', w)
     }
 
@@ -72,12 +99,19 @@ impl Formatter for BoringFormatter {
 
     fn suite_finished(
         &mut self,
-        result: &SuiteResult,
+        _result: &SuiteResult,
         _success: bool,
         w: &mut dyn Write,
     ) -> io::Result<()> {
-        writeln!(w, "\n")?;
+        // End the line of dots; the summary waits for the last binary
+        self.binary = None;
+        writeln!(w)?;
+        w.flush()
+    }
 
+    fn run_finished(&mut self, summary: &RunSummary, w: &mut dyn Write) -> io::Result<()> {
+        let result = summary.total();
+        writeln!(w)?;
         let total = result.passed + result.failed + result.ignored;
         if let Some(t) = result.exec_time {
             let tests_per_sec = total as f64 / t;
@@ -90,11 +124,51 @@ impl Formatter for BoringFormatter {
         }
 
         writeln!(w)?;
-        write_colored_summary(result, w)?;
+        write_shard(summary, w)?;
+        write_not_run(summary, w)?;
+        write_breakdown(summary, Some(self.palette), w)?;
+        write_colored_summary(&result, self.palette, w)?;
         writeln!(w)?;
-        write_colored_failures(&self.failures, w)?;
-
+        write_failures(&self.failures, summary, Some(self.palette), w)?;
         w.flush()?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{Depth, Theme};
+    use crate::formatter::run_two_binaries;
+
+    #[test]
+    fn the_summary_adds_up_every_binary() {
+        let palette = Palette::with_depth(Theme::Default, Depth::Ansi16);
+        assert_eq!(
+            run_two_binaries(&mut BoringFormatter::new(palette)),
+            "
+Running 2 tests in auth
+
+.X
+
+Running 1 tests in parse
+
+*
+
+3 tests run in 0.7500s, 4.0 tests/s
+
+  auth   1 passed, 1 failed, 0 ignored  0.50s
+  parse  0 passed, 0 failed, 1 ignored  0.25s
+
+1 passed, 1 failed, 1 ignored
+
+Failures:
+
+  1. login::rejects (0.50s)
+     boom
+     rerun: cargo test --manifest-path /work/Cargo.toml --test auth -- --exact login::rejects
+
+"
+        );
+    }
+}
@@ -1,11 +1,18 @@
-use super::{FailedTest, Formatter};
+use super::{
+    FailedTest, Formatter, write_breakdown, write_failures, write_not_run, write_section,
+    write_shard, write_shuffle_seed,
+};
 use crate::event::SuiteResult;
+use crate::meta::Catalog;
+use crate::summary::RunSummary;
 use std::io::{self, Write};
 
 /// No-color formatter for piped/non-TTY output.
 pub struct DefaultFormatter {
     dot_count: usize,
     failures: Vec<FailedTest>,
+    catalog: Catalog,
+    binary: Option<String>,
 }
 
 impl DefaultFormatter {
@@ -13,6 +20,8 @@ impl DefaultFormatter {
         Self {
             dot_count: 0,
             failures: Vec::new(),
+            catalog: Catalog::default(),
+            binary: None,
         }
     }
 
@@ -27,12 +36,31 @@ impl DefaultFormatter {
     }
 }
 
+impl Default for DefaultFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Formatter for DefaultFormatter {
-    fn suite_started(&mut self, test_count: usize, w: &mut dyn Write) -> io::Result<()> {
-        writeln!(w, "\nRunning {test_count} tests\n")?;
+    fn metadata(&mut self, catalog: &Catalog) {
+        self.catalog = catalog.clone();
+    }
+
+    fn binary_started(&mut self, label: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.binary = Some(label.to_string());
         Ok(())
     }
 
+    fn suite_started(&mut self, test_count: usize, w: &mut dyn Write) -> io::Result<()> {
+        self.dot_count = 0;
+        write_section(self.binary.as_deref(), test_count, w)
+    }
+
+    fn suite_shuffled(&mut self, seed: u64, w: &mut dyn Write) -> io::Result<()> {
+        write_shuffle_seed(seed, w)
+    }
+
     fn test_started(&mut self, _name: &str, _w: &mut dyn Write) -> io::Result<()> {
         Ok(())
     }
@@ -54,12 +82,14 @@ impl Formatter for DefaultFormatter {
         message: Option<&str>,
         w: &mut dyn Write,
     ) -> io::Result<()> {
-        self.failures.push(FailedTest {
-            name: name.to_string(),
+        self.failures.push(FailedTest::new(
+            self.binary.as_deref(),
+            name,
             exec_time,
-            stdout: stdout.map(String::from),
-            message: message.map(String::from),
-        });
+            stdout,
+            message,
+            &self.catalog,
+        ));
         self.emit_dot('X', w)
     }
 
@@ -69,12 +99,22 @@ impl Formatter for DefaultFormatter {
 
     fn suite_finished(
         &mut self,
-        result: &SuiteResult,
+        _result: &SuiteResult,
         _success: bool,
         w: &mut dyn Write,
     ) -> io::Result<()> {
-        writeln!(w, "\n")?;
+        // End the line of dots; the summary waits for the last binary
+        self.binary = None;
+        writeln!(w)?;
+        w.flush()
+    }
 
+    fn run_finished(&mut self, summary: &RunSummary, w: &mut dyn Write) -> io::Result<()> {
+        let result = summary.total();
+        writeln!(w)?;
+        write_shard(summary, w)?;
+        write_not_run(summary, w)?;
+        write_breakdown(summary, None, w)?;
         writeln!(
             w,
             "{} passed, {} failed, {} ignored",
@@ -87,31 +127,43 @@ impl Formatter for DefaultFormatter {
 
         writeln!(w)?;
 
-        if !self.failures.is_empty() {
-            writeln!(w, "Failures:\n")?;
-            for (i, fail) in self.failures.iter().enumerate() {
-                if let Some(t) = fail.exec_time {
-                    writeln!(w, "  {}. {} ({t:.2}s)", i + 1, fail.name)?;
-                } else {
-                    writeln!(w, "  {}. {}", i + 1, fail.name)?;
-                }
-                if let Some(ref msg) = fail.message {
-                    writeln!(w, "     {msg}")?;
-                }
-                if let Some(ref stdout) = fail.stdout {
-                    let trimmed = stdout.trim();
-                    if !trimmed.is_empty() {
-                        writeln!(w, "     --- stdout ---")?;
-                        for line in trimmed.lines() {
-                            writeln!(w, "     {line}")?;
-                        }
-                    }
-                }
-                writeln!(w)?;
-            }
-        }
-
+        write_failures(&self.failures, summary, None, w)?;
         w.flush()?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formatter::run_two_binaries;
+
+    #[test]
+    fn the_summary_adds_up_every_binary() {
+        assert_eq!(
+            run_two_binaries(&mut DefaultFormatter::new()),
+            "
+Running 2 tests in auth
+
+.X
+
+Running 1 tests in parse
+
+*
+
+  auth   1 passed, 1 failed, 0 ignored  0.50s
+  parse  0 passed, 0 failed, 1 ignored  0.25s
+
+1 passed, 1 failed, 1 ignored
+Finished in 0.75s
+
+Failures:
+
+  1. login::rejects (0.50s)
+     boom
+     rerun: cargo test --manifest-path /work/Cargo.toml --test auth -- --exact login::rejects
+
+"
+        );
+    }
+}
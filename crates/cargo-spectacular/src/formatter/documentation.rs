@@ -0,0 +1,303 @@
+use super::{
+    FailedTest, Formatter, reset, write_breakdown, write_colored_summary, write_failures,
+    write_not_run, write_section, write_shard, write_shuffle_seed,
+};
+use crate::color::{Palette, Role};
+use crate::event::SuiteResult;
+use crate::meta::{Catalog, TestMeta};
+use crate::summary::RunSummary;
+use crate::tree::{self, Node};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::Instant;
+
+/// Outcome of a single test, as shown in the tree.
+#[derive(Clone, Copy)]
+enum Outcome {
+    Passed,
+    Failed,
+    Ignored,
+}
+
+/// A finished test, waiting for its suite to end.
+struct Entry {
+    name: String,
+    outcome: Outcome,
+    duration: Option<f64>,
+    /// Why an ignored test is ignored, from `#[ignore = "..."]`.
+    reason: Option<String>,
+}
+
+/// RSpec-style `-fd` output: an indented tree of groups and tests.
+///
+/// Tests finish in any order when libtest runs them in parallel, so each
+/// binary's tree is printed once its suite finishes. Group and test labels
+/// come from the original `describe`/`it` text when spectacular embedded it,
+/// otherwise from the segments of the libtest name.
+pub struct DocumentationFormatter {
+    color: Option<Palette>,
+    catalog: Catalog,
+    binary: Option<String>,
+    started: HashMap<String, Instant>,
+    entries: Vec<Entry>,
+    failures: Vec<FailedTest>,
+}
+
+impl DocumentationFormatter {
+    pub fn new(color: Option<Palette>) -> Self {
+        Self {
+            color,
+            catalog: Catalog::default(),
+            binary: None,
+            started: HashMap::new(),
+            entries: Vec::new(),
+            failures: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, name: &str, outcome: Outcome, exec_time: Option<f64>) {
+        let measured = self
+            .started
+            .remove(name)
+            .map(|start| start.elapsed().as_secs_f64());
+        self.entries.push(Entry {
+            name: name.to_string(),
+            outcome,
+            duration: exec_time.or(measured),
+            reason: None,
+        });
+    }
+
+    /// The metadata of test `name` in the binary whose suite is running.
+    fn meta(&self, name: &str) -> Option<&TestMeta> {
+        self.catalog.get(self.binary.as_deref(), name)
+    }
+
+    /// Where `entry` sits in the tree of its binary.
+    fn node<'a>(&'a self, entry: &'a Entry) -> Node<'a> {
+        let meta = self.meta(&entry.name);
+        Node {
+            module: parent(&entry.name),
+            group: meta.and_then(|m| m.group.as_deref()),
+            line: meta.and_then(|m| m.line),
+            name: &entry.name,
+        }
+    }
+
+    fn write_entry(&self, entry: &Entry, w: &mut dyn Write) -> io::Result<()> {
+        let label = self
+            .meta(&entry.name)
+            .and_then(|m| m.description.as_deref())
+            .unwrap_or_else(|| leaf(&entry.name));
+        let (marker, color) = match entry.outcome {
+            Outcome::Passed => ("✓", Role::Success),
+            Outcome::Failed => ("✗", Role::Failure),
+            Outcome::Ignored => ("○", Role::Warning),
+        };
+        if let Some(palette) = self.color {
+            palette.paint(w, color)?;
+        }
+        write!(w, "{marker} {label}")?;
+        if let Outcome::Ignored = entry.outcome {
+            match entry.reason {
+                Some(ref reason) => write!(w, " (pending: {reason})")?,
+                None => write!(w, " (pending)")?,
+            }
+        }
+        if self.color.is_some() {
+            reset(w)?;
+        }
+        if let Some(t) = entry.duration
+            && !matches!(entry.outcome, Outcome::Ignored)
+        {
+            write!(w, " ({})", format_duration(t))?;
+        }
+        writeln!(w)
+    }
+
+    fn write_tree(&self, w: &mut dyn Write) -> io::Result<()> {
+        tree::write(
+            &self.entries,
+            |e| self.node(e),
+            |e, w| self.write_entry(e, w),
+            w,
+        )
+    }
+}
+
+/// The module path of a libtest name, without the test itself.
+fn parent(name: &str) -> &str {
+    name.rsplit_once("::").map_or("", |(parent, _)| parent)
+}
+
+/// The test function name at the end of a libtest name.
+fn leaf(name: &str) -> &str {
+    name.rsplit_once("::").map_or(name, |(_, leaf)| leaf)
+}
+
+fn format_duration(secs: f64) -> String {
+    if secs < 1.0 {
+        format!("{}ms", (secs * 1000.0).round() as u64)
+    } else {
+        format!("{secs:.2}s")
+    }
+}
+
+impl Formatter for DocumentationFormatter {
+    fn metadata(&mut self, catalog: &Catalog) {
+        self.catalog = catalog.clone();
+    }
+
+    fn binary_started(&mut self, label: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.binary = Some(label.to_string());
+        Ok(())
+    }
+
+    fn suite_started(&mut self, test_count: usize, w: &mut dyn Write) -> io::Result<()> {
+        write_section(self.binary.as_deref(), test_count, w)
+    }
+
+    fn suite_shuffled(&mut self, seed: u64, w: &mut dyn Write) -> io::Result<()> {
+        write_shuffle_seed(seed, w)
+    }
+
+    fn test_started(&mut self, name: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.started.insert(name.to_string(), Instant::now());
+        Ok(())
+    }
+
+    fn test_passed(
+        &mut self,
+        name: &str,
+        exec_time: Option<f64>,
+        _w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.record(name, Outcome::Passed, exec_time);
+        Ok(())
+    }
+
+    fn test_failed(
+        &mut self,
+        name: &str,
+        exec_time: Option<f64>,
+        stdout: Option<&str>,
+        message: Option<&str>,
+        _w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.failures.push(FailedTest::new(
+            self.binary.as_deref(),
+            name,
+            exec_time,
+            stdout,
+            message,
+            &self.catalog,
+        ));
+        self.record(name, Outcome::Failed, exec_time);
+        Ok(())
+    }
+
+    fn test_ignored(&mut self, name: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.record(name, Outcome::Ignored, None);
+        Ok(())
+    }
+
+    fn test_ignored_with_message(
+        &mut self,
+        name: &str,
+        message: Option<&str>,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.test_ignored(name, w)?;
+        if let Some(entry) = self.entries.last_mut() {
+            entry.reason = message.map(String::from);
+        }
+        Ok(())
+    }
+
+    fn suite_finished(
+        &mut self,
+        _result: &SuiteResult,
+        _success: bool,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.write_tree(w)?;
+        self.entries.clear();
+        self.started.clear();
+        self.binary = None;
+        w.flush()
+    }
+
+    fn run_finished(&mut self, summary: &RunSummary, w: &mut dyn Write) -> io::Result<()> {
+        let result = summary.total();
+        writeln!(w)?;
+        write_shard(summary, w)?;
+        write_not_run(summary, w)?;
+        write_breakdown(summary, self.color, w)?;
+
+        if let Some(palette) = self.color {
+            write_colored_summary(&result, palette, w)?;
+        } else {
+            writeln!(
+                w,
+                "{} passed, {} failed, {} ignored",
+                result.passed, result.failed, result.ignored
+            )?;
+        }
+        if let Some(t) = result.exec_time {
+            writeln!(w, "Finished in {t:.2}s")?;
+        }
+        writeln!(w)?;
+
+        write_failures(&self.failures, summary, self.color, w)?;
+
+        w.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_split_into_groups_and_tests() {
+        assert_eq!(parent("auth::login::accepts"), "auth::login");
+        assert_eq!(leaf("auth::login::accepts"), "accepts");
+        assert_eq!(parent("smoke"), "");
+        assert_eq!(leaf("smoke"), "smoke");
+        assert_eq!(format_duration(0.0124), "12ms");
+        assert_eq!(format_duration(1.5), "1.50s");
+    }
+
+    #[test]
+    fn a_suite_prints_as_a_tree_once_it_finishes() {
+        let mut f = DocumentationFormatter::new(None);
+        let mut out = Vec::new();
+        f.binary_started("auth", &mut out).unwrap();
+        f.suite_started(4, &mut out).unwrap();
+        // Finished out of order, as under parallel threads
+        f.test_passed("login::rejects", Some(0.002), &mut out)
+            .unwrap();
+        f.test_ignored_with_message("logout::slow", Some("flaky"), &mut out)
+            .unwrap();
+        f.test_failed("login::accepts", Some(1.25), None, None, &mut out)
+            .unwrap();
+        f.test_passed("smoke", Some(0.0), &mut out).unwrap();
+        f.suite_finished(&SuiteResult::default(), false, &mut out)
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "
+Running 4 tests in auth
+
+  ✓ smoke (0ms)
+  login
+    ✗ accepts (1.25s)
+    ✓ rejects (2ms)
+  logout
+    ○ slow (pending: flaky)
+"
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use super::Formatter;
+use crate::event::{BenchResult, DocTestTimes, SuiteResult};
+use crate::meta::{Catalog, TestMeta};
+use crate::summary::RunSummary;
+use serde::Serialize;
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// Version of the event protocol; bumped when a change would break plugins.
+pub const PROTOCOL: u32 = 1;
+
+/// Prefix of formatter plugins found on `PATH`: `--format junit2` runs
+/// `spectacular-formatter-junit2`.
+const PLUGIN_PREFIX: &str = "spectacular-formatter-";
+
+/// One line of the protocol, as a JSON object tagged by `"event"`.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Message<'a> {
+    /// Always first.
+    Hello {
+        protocol: u32,
+        color: bool,
+    },
+    Metadata {
+        tests: Vec<TestInfo<'a>>,
+    },
+    RunStarted {
+        test_count: usize,
+    },
+    BinaryStarted {
+        label: &'a str,
+    },
+    SuiteStarted {
+        test_count: usize,
+    },
+    SuiteShuffled {
+        seed: u64,
+    },
+    TestStarted {
+        name: &'a str,
+    },
+    TestPassed {
+        name: &'a str,
+        exec_time: Option<f64>,
+    },
+    TestFailed {
+        name: &'a str,
+        exec_time: Option<f64>,
+        stdout: Option<&'a str>,
+        message: Option<&'a str>,
+    },
+    TestOutput {
+        name: &'a str,
+        stdout: &'a str,
+    },
+    TestIgnored {
+        name: &'a str,
+        message: Option<&'a str>,
+    },
+    TestAllowedFail {
+        name: &'a str,
+    },
+    TestTimeout {
+        name: &'a str,
+    },
+    Bench {
+        #[serde(flatten)]
+        result: &'a BenchResult,
+    },
+    DocTestTimes {
+        total_time: f64,
+        compilation_time: f64,
+    },
+    SuiteFinished {
+        success: bool,
+        #[serde(flatten)]
+        result: &'a SuiteResult,
+    },
+    /// Followed only by any reports.
+    RunFinished {
+        #[serde(flatten)]
+        total: SuiteResult,
+        binaries: Vec<BinaryResult<'a>>,
+        not_run: Option<usize>,
+    },
+    Report {
+        text: &'a str,
+    },
+}
+
+/// A test's metadata, with the label of the binary it is in.
+#[derive(Serialize)]
+struct TestInfo<'a> {
+    binary: &'a str,
+    #[serde(flatten)]
+    meta: &'a TestMeta,
+}
+
+#[derive(Serialize)]
+struct BinaryResult<'a> {
+    label: &'a str,
+    #[serde(flatten)]
+    result: &'a SuiteResult,
+    failed_tests: &'a [String],
+}
+
+/// Hands every event to an external program as JSON lines on its stdin.
+///
+/// The program's stdout and stderr are the runner's, so whatever it prints
+/// is the output. It is started with the first event and must exit once
+/// its stdin closes at the end of the run; a failing exit fails the run.
+pub struct ExecFormatter {
+    command: Vec<String>,
+    color: bool,
+    child: Option<(Child, ChildStdin)>,
+    /// Lines waiting for the program to start.
+    pending: Vec<String>,
+}
+
+impl ExecFormatter {
+    pub fn new(command: Vec<String>, color: bool) -> Self {
+        Self {
+            command,
+            color,
+            child: None,
+            pending: Vec::new(),
+        }
+    }
+
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        let line = serde_json::to_string(message)?;
+        let stdin = match self.child {
+            Some((_, ref mut stdin)) => stdin,
+            None => self.start()?,
+        };
+        writeln!(stdin, "{line}")
+            .and_then(|()| stdin.flush())
+            .map_err(|e| stopped(&self.command[0], e))
+    }
+
+    fn start(&mut self) -> io::Result<&mut ChildStdin> {
+        let (program, args) = self
+            .command
+            .split_first()
+            .ok_or_else(|| io::Error::other("exec: formatter needs a command"))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .env("SPECTACULAR_PROTOCOL", PROTOCOL.to_string())
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("failed to start formatter {program}: {e}"),
+                )
+            })?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+
+        let hello = Message::Hello {
+            protocol: PROTOCOL,
+            color: self.color,
+        };
+        let mut lines = vec![serde_json::to_string(&hello)?];
+        lines.append(&mut self.pending);
+        for line in lines {
+            writeln!(stdin, "{line}").map_err(|e| stopped(program, e))?;
+        }
+        let (_, stdin) = self.child.insert((child, stdin));
+        Ok(stdin)
+    }
+
+    /// Close the program's stdin and wait for it to finish writing.
+    fn close(&mut self) -> io::Result<()> {
+        let Some((mut child, stdin)) = self.child.take() else {
+            return Ok(());
+        };
+        drop(stdin);
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "formatter {} exited with {status}",
+                self.command[0]
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ExecFormatter {
+    fn drop(&mut self) {
+        // A run that stopped early still lets the program wind down
+        let _ = self.close();
+    }
+}
+
+impl Formatter for ExecFormatter {
+    fn metadata(&mut self, catalog: &Catalog) {
+        let mut tests: Vec<TestInfo> = catalog
+            .tests()
+            .map(|(binary, meta)| TestInfo { binary, meta })
+            .collect();
+        tests.sort_by(|a, b| (a.binary, &a.meta.path).cmp(&(b.binary, &b.meta.path)));
+        let message = Message::Metadata { tests };
+        if let Ok(line) = serde_json::to_string(&message) {
+            self.pending.push(line);
+        }
+    }
+
+    // The protocol promises plugins the count
+    fn wants_test_count(&self) -> bool {
+        true
+    }
+
+    fn run_started(&mut self, test_count: Option<usize>, _w: &mut dyn Write) -> io::Result<()> {
+        let test_count = test_count.unwrap_or_default();
+        self.send(&Message::RunStarted { test_count })
+    }
+
+    fn binary_started(&mut self, label: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.send(&Message::BinaryStarted { label })
+    }
+
+    fn suite_started(&mut self, test_count: usize, _w: &mut dyn Write) -> io::Result<()> {
+        self.send(&Message::SuiteStarted { test_count })
+    }
+
+    fn suite_shuffled(&mut self, seed: u64, _w: &mut dyn Write) -> io::Result<()> {
+        self.send(&Message::SuiteShuffled { seed })
+    }
+
+    fn test_started(&mut self, name: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.send(&Message::TestStarted { name })
+    }
+
+    fn test_passed(
+        &mut self,
+        name: &str,
+        exec_time: Option<f64>,
+        _w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.send(&Message::TestPassed { name, exec_time })
+    }
+
+    fn test_failed(
+        &mut self,
+        name: &str,
+        exec_time: Option<f64>,
+        stdout: Option<&str>,
+        message: Option<&str>,
+        _w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.send(&Message::TestFailed {
+            name,
+            exec_time,
+            stdout,
+            message,
+        })
+    }
+
+    fn test_output(&mut self, name: &str, stdout: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.send(&Message::TestOutput { name, stdout })
+    }
+
+    fn test_ignored(&mut self, name: &str, w: &mut dyn Write) -> io::Result<()> {
+        self.test_ignored_with_message(name, None, w)
+    }
+
+    fn test_ignored_with_message(
+        &mut self,
+        name: &str,
+        message: Option<&str>,
+        _w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.send(&Message::TestIgnored { name, message })
+    }
+
+    fn test_allowed_fail(&mut self, name: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.send(&Message::TestAllowedFail { name })
+    }
+
+    fn test_timeout(&mut self, name: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.send(&Message::TestTimeout { name })
+    }
+
+    fn bench_result(&mut self, result: &BenchResult, _w: &mut dyn Write) -> io::Result<()> {
+        self.send(&Message::Bench { result })
+    }
+
+    fn doc_test_times(&mut self, times: &DocTestTimes, _w: &mut dyn Write) -> io::Result<()> {
+        self.send(&Message::DocTestTimes {
+            total_time: times.total_time,
+            compilation_time: times.compilation_time,
+        })
+    }
+
+    fn suite_finished(
+        &mut self,
+        result: &SuiteResult,
+        success: bool,
+        _w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.send(&Message::SuiteFinished { success, result })
+    }
+
+    fn run_finished(&mut self, summary: &RunSummary, _w: &mut dyn Write) -> io::Result<()> {
+        let binaries = summary
+            .binaries
+            .iter()
+            .map(|b| BinaryResult {
+                label: &b.label,
+                result: &b.result,
+                failed_tests: &b.failed,
+            })
+            .collect();
+        self.send(&Message::RunFinished {
+            total: summary.total(),
+            binaries,
+            not_run: summary.not_run,
+        })
+    }
+
+    fn report(&mut self, text: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.send(&Message::Report { text })
+    }
+
+    fn finish(&mut self, _w: &mut dyn Write) -> io::Result<()> {
+        self.close()
+    }
+}
+
+/// A closed pipe means the program exited without reading the whole run.
+fn stopped(program: &str, e: io::Error) -> io::Error {
+    match e.kind() {
+        io::ErrorKind::BrokenPipe => {
+            io::Error::other(format!("formatter {program} stopped reading events"))
+        }
+        _ => e,
+    }
+}
+
+/// `spectacular-formatter-<name>` in one of the `path` directories (`PATH`), if there is one.
+pub fn find_plugin(name: &str, path: &OsStr) -> Option<PathBuf> {
+    let file_name = format!("{PLUGIN_PREFIX}{name}{}", std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(path)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+}
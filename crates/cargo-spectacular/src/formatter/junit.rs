@@ -0,0 +1,376 @@
+use super::{Formatter, panic_message};
+use crate::diagnostics::strip_ansi;
+use crate::event::SuiteResult;
+use crate::meta::Catalog;
+use crate::summary::RunSummary;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// A finished test case, as recorded for the report.
+struct Case {
+    /// `<binary>::<module path>`, or just the binary at the crate root.
+    classname: String,
+    name: String,
+    time: Option<f64>,
+    outcome: Outcome,
+}
+
+enum Outcome {
+    Passed,
+    Failed {
+        message: Option<String>,
+        stdout: Option<String>,
+    },
+    Skipped {
+        message: Option<String>,
+    },
+}
+
+/// Writes a JUnit XML report alongside the terminal formatter.
+///
+/// The tests at the root of a binary, and those of each group module inside
+/// it, become a flat `<testsuite>` named like their classname. The file is
+/// rewritten when the run starts, after every suite and when it ends, so a
+/// run that stops early or never builds doesn't leave a stale report.
+pub struct JunitReport {
+    path: PathBuf,
+    catalog: Catalog,
+    binary: String,
+    started: HashMap<String, Instant>,
+    cases: Vec<Case>,
+}
+
+impl JunitReport {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            catalog: Catalog::default(),
+            binary: String::from("tests"),
+            started: HashMap::new(),
+            cases: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, name: &str, exec_time: Option<f64>, outcome: Outcome) {
+        let measured = self
+            .started
+            .remove(name)
+            .map(|start| start.elapsed().as_secs_f64());
+        let (module, leaf) = name.rsplit_once("::").unwrap_or(("", name));
+        let classname = if module.is_empty() {
+            self.binary.clone()
+        } else {
+            format!("{}::{module}", self.binary)
+        };
+        let name = self
+            .catalog
+            .get(Some(&self.binary), name)
+            .and_then(|m| m.description.clone())
+            .unwrap_or_else(|| leaf.to_string());
+        self.cases.push(Case {
+            classname,
+            name,
+            time: exec_time.or(measured),
+            outcome,
+        });
+    }
+
+    fn render(&self) -> String {
+        // Suites in the order their first test finished
+        let mut suites: Vec<(&str, Vec<&Case>)> = Vec::new();
+        for case in &self.cases {
+            match suites.iter_mut().find(|(name, _)| *name == case.classname) {
+                Some((_, cases)) => cases.push(case),
+                None => suites.push((&case.classname, vec![case])),
+            }
+        }
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"cargo spectacular\" {}>",
+            counts(&self.cases.iter().collect::<Vec<_>>())
+        );
+        for (name, cases) in &suites {
+            let _ = writeln!(
+                xml,
+                "  <testsuite name=\"{}\" {}>",
+                escape(name),
+                counts(cases)
+            );
+            for case in cases {
+                let _ = write!(
+                    xml,
+                    "    <testcase name=\"{}\" classname=\"{}\"",
+                    escape(&case.name),
+                    escape(&case.classname)
+                );
+                // Left out rather than made up when libtest didn't time the test
+                if let Some(time) = case.time {
+                    let _ = write!(xml, " time=\"{time:.3}\"");
+                }
+                match case.outcome {
+                    Outcome::Passed => xml.push_str("/>\n"),
+                    Outcome::Skipped { message: None } => {
+                        xml.push_str(">\n      <skipped/>\n    </testcase>\n");
+                    }
+                    Outcome::Skipped {
+                        message: Some(ref message),
+                    } => {
+                        let _ = write!(
+                            xml,
+                            ">\n      <skipped message=\"{}\"/>\n    </testcase>\n",
+                            escape(message)
+                        );
+                    }
+                    Outcome::Failed {
+                        ref message,
+                        ref stdout,
+                    } => {
+                        xml.push_str(">\n");
+                        let message = message.as_deref().unwrap_or("test failed");
+                        let summary = message.lines().next().unwrap_or_default();
+                        let _ = writeln!(
+                            xml,
+                            "      <failure message=\"{}\">{}</failure>",
+                            escape(summary),
+                            escape(message)
+                        );
+                        if let Some(stdout) = stdout
+                            && !stdout.trim().is_empty()
+                        {
+                            let _ =
+                                writeln!(xml, "      <system-out>{}</system-out>", escape(stdout));
+                        }
+                        xml.push_str("    </testcase>\n");
+                    }
+                }
+            }
+            xml.push_str("  </testsuite>\n");
+        }
+        xml.push_str("</testsuites>\n");
+        xml
+    }
+
+    fn write_report(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent()
+            && !dir.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&self.path, self.render()).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Failed to write JUnit report {}: {e}", self.path.display()),
+            )
+        })
+    }
+}
+
+/// `tests`, `failures`, `skipped` and `time` attributes for a set of cases.
+fn counts(cases: &[&Case]) -> String {
+    let failures = cases
+        .iter()
+        .filter(|c| matches!(c.outcome, Outcome::Failed { .. }))
+        .count();
+    let skipped = cases
+        .iter()
+        .filter(|c| matches!(c.outcome, Outcome::Skipped { .. }))
+        .count();
+    // Summing from 0.0: an empty `sum` of floats is -0.0
+    let time = cases.iter().filter_map(|c| c.time).fold(0.0, |a, t| a + t);
+    format!(
+        "tests=\"{}\" failures=\"{failures}\" errors=\"0\" skipped=\"{skipped}\" time=\"{time:.3}\"",
+        cases.len()
+    )
+}
+
+/// Escape text for XML attributes and content, dropping characters XML 1.0
+/// forbids and the color codes of colored panic output.
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in strip_ansi(s).chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c.is_control() => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+impl Formatter for JunitReport {
+    fn metadata(&mut self, catalog: &Catalog) {
+        self.catalog = catalog.clone();
+    }
+
+    fn run_started(&mut self, _test_count: Option<usize>, _w: &mut dyn Write) -> io::Result<()> {
+        self.write_report()
+    }
+
+    fn binary_started(&mut self, label: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.binary = label.to_string();
+        Ok(())
+    }
+
+    fn suite_started(&mut self, _test_count: usize, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn test_started(&mut self, name: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.started.insert(name.to_string(), Instant::now());
+        Ok(())
+    }
+
+    fn test_passed(
+        &mut self,
+        name: &str,
+        exec_time: Option<f64>,
+        _w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.record(name, exec_time, Outcome::Passed);
+        Ok(())
+    }
+
+    fn test_failed(
+        &mut self,
+        name: &str,
+        exec_time: Option<f64>,
+        stdout: Option<&str>,
+        message: Option<&str>,
+        _w: &mut dyn Write,
+    ) -> io::Result<()> {
+        let message = message
+            .map(String::from)
+            .or_else(|| stdout.and_then(panic_message));
+        let stdout = stdout.map(String::from);
+        self.record(name, exec_time, Outcome::Failed { message, stdout });
+        Ok(())
+    }
+
+    fn test_ignored(&mut self, name: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.record(name, None, Outcome::Skipped { message: None });
+        Ok(())
+    }
+
+    fn test_ignored_with_message(
+        &mut self,
+        name: &str,
+        message: Option<&str>,
+        _w: &mut dyn Write,
+    ) -> io::Result<()> {
+        let message = message.map(String::from);
+        self.record(name, None, Outcome::Skipped { message });
+        Ok(())
+    }
+
+    fn suite_finished(
+        &mut self,
+        _result: &SuiteResult,
+        _success: bool,
+        _w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.started.clear();
+        self.write_report()
+    }
+
+    fn run_finished(&mut self, _summary: &RunSummary, _w: &mut dyn Write) -> io::Result<()> {
+        self.write_report()
+    }
+
+    /// The runner's reports are for the terminal; the XML has no place for them.
+    fn report(&mut self, _text: &str, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self, _w: &mut dyn Write) -> io::Result<()> {
+        self.write_report()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::scratch;
+
+    fn report() -> JunitReport {
+        let mut report = JunitReport::new("junit.xml");
+        report.binary_started("auth", &mut io::sink()).unwrap();
+        report
+    }
+
+    #[test]
+    fn untimed_cases_have_no_time() {
+        let mut report = report();
+        let w = &mut io::sink();
+        report.test_passed("login::works", None, w).unwrap();
+        report.test_ignored("login::later", w).unwrap();
+        let xml = report.render();
+
+        assert!(xml.contains(r#"<testcase name="works" classname="auth::login"/>"#));
+        assert!(!xml.contains("-0.000"), "{xml}");
+        assert!(xml.contains(r#"skipped="1" time="0.000">"#), "{xml}");
+    }
+
+    #[test]
+    fn timed_cases_add_up() {
+        let mut report = report();
+        let w = &mut io::sink();
+        report.test_passed("works", Some(0.25), w).unwrap();
+        report.test_passed("also_works", Some(0.5), w).unwrap();
+        report.test_passed("untimed", None, w).unwrap();
+        let xml = report.render();
+
+        assert!(xml.contains(r#"<testcase name="works" classname="auth" time="0.250"/>"#));
+        assert!(xml.contains(r#"<testcase name="untimed" classname="auth"/>"#));
+        assert!(xml.contains(
+            r#"<testsuite name="auth" tests="3" failures="0" errors="0" skipped="0" time="0.750">"#
+        ));
+    }
+
+    #[test]
+    fn failures_lose_their_colors() {
+        let mut report = report();
+        let stdout = "thread 'x' panicked at src/lib.rs:1:1:\n\x1b[31mboom\x1b[0m <here>\n";
+        report
+            .test_failed("x", None, Some(stdout), None, &mut io::sink())
+            .unwrap();
+        let xml = report.render();
+
+        assert!(
+            xml.contains(r#"<failure message="boom &lt;here&gt;">"#),
+            "{xml}"
+        );
+        assert!(!xml.contains("[31m") && !xml.contains("[0m"), "{xml}");
+    }
+
+    #[test]
+    fn finish_replaces_a_stale_report() {
+        let path = scratch("junit-stale").join("junit.xml");
+        std::fs::write(&path, "<testsuites/>").unwrap();
+        let mut report = JunitReport::new(&path);
+        report.finish(&mut io::sink()).unwrap();
+        let xml = std::fs::read_to_string(&path).unwrap();
+
+        assert!(
+            xml.contains(r#"<testsuites name="cargo spectacular" tests="0""#),
+            "{xml}"
+        );
+    }
+
+    #[test]
+    fn escape_drops_forbidden_characters() {
+        assert_eq!(
+            escape("a\u{7}b\t\"c\" & 'd'"),
+            "ab\t&quot;c&quot; &amp; &apos;d&apos;"
+        );
+    }
+}
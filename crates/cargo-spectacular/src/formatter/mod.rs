@@ -1,28 +1,220 @@
 pub mod boring;
 pub mod default;
+pub mod documentation;
+pub mod exec;
+pub mod junit;
 pub mod pride;
+pub mod progress;
+pub mod tap;
 
-use crate::event::SuiteResult;
+use crate::color::{Palette, Role};
+use crate::event::{BenchResult, DocTestTimes, SuiteResult};
+use crate::meta::Catalog;
+use crate::summary::RunSummary;
+use std::ffi::OsStr;
 use std::io::{self, Write};
+use std::path::PathBuf;
+
+pub use crate::color::reset;
 
 /// Captured failure for replay in the summary.
+#[non_exhaustive]
 pub struct FailedTest {
+    /// The label of the test binary the test is in, when the runner named it.
+    pub binary: Option<String>,
     pub name: String,
+    /// The original `describe`/`it` sentence, when the test was written with `it`.
+    pub description: Option<String>,
+    /// `file:line` where the test is declared.
+    pub location: Option<String>,
     pub exec_time: Option<f64>,
     pub stdout: Option<String>,
     pub message: Option<String>,
+    /// Where the test panicked, from the panic message.
+    pub(crate) panic_location: Option<PanicLocation>,
+    /// The source file of `panic_location`, when it could be found.
+    pub(crate) source: Option<PathBuf>,
+}
+
+impl FailedTest {
+    pub fn new(
+        binary: Option<&str>,
+        name: &str,
+        exec_time: Option<f64>,
+        stdout: Option<&str>,
+        message: Option<&str>,
+        catalog: &Catalog,
+    ) -> Self {
+        let panic_location = stdout.and_then(panic_location);
+        let source = panic_location
+            .as_ref()
+            .and_then(|loc| catalog.source_file(binary, &loc.file));
+        Self {
+            binary: binary.map(String::from),
+            name: name.to_string(),
+            description: catalog.description(binary, name),
+            location: catalog.location(binary, name),
+            exec_time,
+            stdout: stdout.map(String::from),
+            message: message.map(String::from),
+            panic_location,
+            source,
+        }
+    }
+
+    /// The heading for the failure replay: the description when known, else the name.
+    pub fn title(&self) -> &str {
+        self.description.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The line under a described failure: libtest name plus declaration site.
+    pub fn origin(&self) -> Option<String> {
+        self.description.as_ref()?;
+        Some(match self.location {
+            Some(ref loc) => format!("{} ({loc})", self.name),
+            None => self.name.clone(),
+        })
+    }
+}
+
+/// The panic message from a failed test's captured output.
+///
+/// libtest only fills `message` for some failures (e.g. `should_panic`); for
+/// plain panics the message sits in the captured stdout after
+/// `thread '...' panicked at <file>:<line>:<col>:`.
+pub fn panic_message(stdout: &str) -> Option<String> {
+    let mut lines = stdout.lines().skip_while(|l| !l.contains("panicked at"));
+    lines.next()?;
+    let message: Vec<&str> = lines
+        .take_while(|l| !l.starts_with("note: ") && !l.starts_with("stack backtrace:"))
+        .collect();
+    let message = message.join("\n");
+    (!message.trim().is_empty()).then_some(message)
+}
+
+/// `file:line:col` a test panicked at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicLocation {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for PanicLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// Where the panic in a failed test's captured output happened.
+///
+/// Reads `panicked at tests/foo.rs:42:9:`, and the `panicked at 'msg',
+/// tests/foo.rs:42:9` of older toolchains.
+pub fn panic_location(stdout: &str) -> Option<PanicLocation> {
+    let line = stdout.lines().find(|l| l.contains("panicked at "))?;
+    let (_, rest) = line.split_once("panicked at ")?;
+    let rest = rest.trim_end().trim_end_matches(':');
+    let rest = rest.rsplit_once("', ").map_or(rest, |(_, loc)| loc);
+    let mut parts = rest.rsplitn(3, ':');
+    let column = parts.next()?.parse().ok()?;
+    let line = parts.next()?.parse().ok()?;
+    let file = parts.next().filter(|f| !f.is_empty())?;
+    Some(PanicLocation {
+        file: file.to_string(),
+        line,
+        column,
+    })
+}
+
+/// Lines shown before and after the failing one.
+const SNIPPET_CONTEXT: usize = 2;
+
+/// The source around where a failure panicked, with the failing line marked.
+///
+/// Nothing is written when the file can't be read, e.g. for panics inside
+/// the standard library or a dependency.
+fn write_snippet(fail: &FailedTest, color: Option<Palette>, w: &mut dyn Write) -> io::Result<()> {
+    let (Some(loc), Some(path)) = (&fail.panic_location, &fail.source) else {
+        return Ok(());
+    };
+    let Ok(source) = std::fs::read_to_string(path) else {
+        return Ok(());
+    };
+    let lines: Vec<&str> = source.lines().collect();
+    if loc.line == 0 || loc.line > lines.len() {
+        return Ok(());
+    }
+    let first = loc.line.saturating_sub(SNIPPET_CONTEXT).max(1);
+    let last = (loc.line + SNIPPET_CONTEXT).min(lines.len());
+    let width = last.to_string().len();
+
+    writeln!(w, "     # {loc}")?;
+    for number in first..=last {
+        let text = lines[number - 1];
+        if number != loc.line {
+            writeln!(w, "       {number:>width$} | {text}")?;
+        } else if let Some(palette) = color {
+            palette.paint(w, Role::Failure)?;
+            write!(w, "     > {number:>width$} | {text}")?;
+            reset(w)?;
+            writeln!(w)?;
+        } else {
+            writeln!(w, "     > {number:>width$} | {text}")?;
+        }
+    }
+    Ok(())
 }
 
 /// Pluggable test output formatter.
+///
+/// The runner calls these methods in order: `metadata` and `run_started`
+/// once, then as libtest reports each binary `binary_started`,
+/// `suite_started`, the test events, `suite_finished`. Once every binary has
+/// run come `run_finished`, any `report`s, and `finish`. Output goes to `w`,
+/// the runner's stdout.
 pub trait Formatter {
+    /// Spectacular metadata for the tests in this run, sent once before any suite starts.
+    fn metadata(&mut self, _catalog: &Catalog) {}
+    /// Whether `run_started` needs the number of tests in the run.
+    ///
+    /// Counting them lists every test binary once more before a plain run,
+    /// so the runner only does it when a formatter asks.
+    fn wants_test_count(&self) -> bool {
+        false
+    }
+    /// The run is about to start `test_count` tests across all its binaries.
+    ///
+    /// The count is `None` when it wasn't known up front and the formatter
+    /// didn't ask for it with [`Formatter::wants_test_count`]. Doc-tests are
+    /// only counted when `--shard` lists them, so the suites may bring more
+    /// tests than this.
+    fn run_started(&mut self, _test_count: Option<usize>, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+    /// A test binary is about to run, labelled by its cargo target.
+    fn binary_started(&mut self, _label: &str, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+    /// A test binary starts running `test_count` tests.
     fn suite_started(&mut self, test_count: usize, w: &mut dyn Write) -> io::Result<()>;
+    /// The binary's tests run in shuffled order; `--shuffle-seed <seed>` repeats it.
+    fn suite_shuffled(&mut self, _seed: u64, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+    /// A test started; libtest runs tests in parallel, so others may be running too.
     fn test_started(&mut self, name: &str, w: &mut dyn Write) -> io::Result<()>;
+    /// `exec_time` is in seconds, when libtest reports it.
     fn test_passed(
         &mut self,
         name: &str,
         exec_time: Option<f64>,
         w: &mut dyn Write,
     ) -> io::Result<()>;
+    /// The captured output of a test that passed, under `--show-output`.
+    fn test_output(&mut self, _name: &str, _stdout: &str, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+    /// `stdout` is the test's captured output, which holds the panic message.
     fn test_failed(
         &mut self,
         name: &str,
@@ -32,76 +224,492 @@ pub trait Formatter {
         w: &mut dyn Write,
     ) -> io::Result<()>;
     fn test_ignored(&mut self, name: &str, w: &mut dyn Write) -> io::Result<()>;
+    /// An ignored test with the reason from `#[ignore = "..."]`, if it gave one.
+    ///
+    /// The runner calls this rather than `test_ignored`; by default it drops the reason.
+    fn test_ignored_with_message(
+        &mut self,
+        name: &str,
+        _message: Option<&str>,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.test_ignored(name, w)
+    }
+    /// A test failed but was allowed to; by default it counts as passed.
+    fn test_allowed_fail(&mut self, name: &str, w: &mut dyn Write) -> io::Result<()> {
+        self.test_passed(name, None, w)
+    }
+    /// A test has been running for over 60 seconds and hasn't finished yet.
+    ///
+    /// The runner already warns about it on stderr.
+    fn test_timeout(&mut self, _name: &str, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+    /// A benchmark finished.
+    fn bench_result(&mut self, _bench: &BenchResult, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+    /// A test binary finished; `success` is false when any of its tests failed.
     fn suite_finished(
         &mut self,
         result: &SuiteResult,
         success: bool,
         w: &mut dyn Write,
     ) -> io::Result<()>;
+    /// How long rustdoc took to compile and run the merged doc-tests that just finished.
+    fn doc_test_times(&mut self, _times: &DocTestTimes, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+    /// Every test binary has run (or the run stopped early).
+    fn run_finished(&mut self, _summary: &RunSummary, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+    /// A section the runner adds after the summary, such as `--profile`'s, as plain text.
+    fn report(&mut self, text: &str, w: &mut dyn Write) -> io::Result<()> {
+        w.write_all(text.as_bytes())?;
+        w.flush()
+    }
+    /// Nothing more will be sent; the last chance to write or fail the run.
+    ///
+    /// This is the only call a formatter gets when the build failed.
+    fn finish(&mut self, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
 }
 
-/// Create a formatter by name.
-pub fn create(name: &str) -> Box<dyn Formatter> {
-    match name {
-        "pride" => Box::new(pride::PrideFormatter::new()),
-        "boring" => Box::new(boring::BoringFormatter::new()),
-        "default" => Box::new(default::DefaultFormatter::new()),
-        _ => {
-            eprintln!("Unknown formatter: {name}, falling back to pride");
-            Box::new(pride::PrideFormatter::new())
+/// Makes a formatter; the argument is the palette to color the output
+/// with, or `None` for plain text.
+pub type Factory = Box<dyn Fn(Option<Palette>) -> Box<dyn Formatter>>;
+
+/// The formatters `--format` can name.
+///
+/// [`Registry::new`] holds the built-in ones. Names that aren't registered
+/// run a plugin: `exec:<command>` runs the command, split on whitespace,
+/// and any other name looks for `spectacular-formatter-<name>` on `PATH`.
+pub struct Registry {
+    factories: Vec<(String, Factory)>,
+}
+
+impl Registry {
+    /// The built-in formatters: pride, boring, default, documentation, tap and progress.
+    ///
+    /// Without color, the dot formatters fall back to the plain `default`
+    /// formatter; `documentation` keeps its tree and drops the color.
+    pub fn new() -> Self {
+        Self {
+            factories: Vec::new(),
+        }
+        .with("pride", |color| -> Box<dyn Formatter> {
+            if let Some(palette) = color {
+                Box::new(pride::PrideFormatter::new(palette))
+            } else {
+                Box::new(default::DefaultFormatter::new())
+            }
+        })
+        .with("boring", |color| -> Box<dyn Formatter> {
+            if let Some(palette) = color {
+                Box::new(boring::BoringFormatter::new(palette))
+            } else {
+                Box::new(default::DefaultFormatter::new())
+            }
+        })
+        .with("default", |_| Box::new(default::DefaultFormatter::new()))
+        .with("documentation", |color| {
+            Box::new(documentation::DocumentationFormatter::new(color))
+        })
+        .with("tap", |_| Box::new(tap::TapFormatter::new()))
+        .with("progress", |color| {
+            Box::new(progress::ProgressFormatter::new(color))
+        })
+    }
+
+    /// Add a formatter under `name`, replacing any registered with that name.
+    pub fn with(
+        mut self,
+        name: impl Into<String>,
+        factory: impl Fn(Option<Palette>) -> Box<dyn Formatter> + 'static,
+    ) -> Self {
+        let name = name.into();
+        self.factories.retain(|(n, _)| *n != name);
+        self.factories.push((name, Box::new(factory)));
+        self
+    }
+
+    /// The registered names, in the order they were added.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Create a formatter by name, falling back to a plugin; an error when
+    /// there is neither.
+    pub fn create(&self, name: &str, color: Option<Palette>) -> io::Result<Box<dyn Formatter>> {
+        self.create_from(name, color, &std::env::var_os("PATH").unwrap_or_default())
+    }
+
+    /// [`Registry::create`], looking for plugins in the `path` directories.
+    fn create_from(
+        &self,
+        name: &str,
+        color: Option<Palette>,
+        path: &OsStr,
+    ) -> io::Result<Box<dyn Formatter>> {
+        if let Some((_, factory)) = self.factories.iter().find(|(n, _)| n == name) {
+            return Ok(factory(color));
+        }
+        if let Some(command) = name.strip_prefix("exec:") {
+            let command = command.split_whitespace().map(String::from).collect();
+            return Ok(Box::new(exec::ExecFormatter::new(command, color.is_some())));
+        }
+        match exec::find_plugin(name, path) {
+            Some(path) => Ok(Box::new(exec::ExecFormatter::new(
+                vec![path.display().to_string()],
+                color.is_some(),
+            ))),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("unknown formatter {name} (no spectacular-formatter-{name} on PATH)"),
+            )),
         }
     }
 }
 
-// ANSI helpers shared by colored formatters (pride, boring).
+impl Default for Registry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-pub fn fg(w: &mut dyn Write, r: u8, g: u8, b: u8) -> io::Result<()> {
-    write!(w, "\x1b[38;2;{r};{g};{b}m")
+/// Sends every event to several formatters: the terminal one, then any reports.
+pub struct Fanout {
+    formatters: Vec<Box<dyn Formatter>>,
 }
 
-pub fn reset(w: &mut dyn Write) -> io::Result<()> {
-    write!(w, "\x1b[0m")
+impl Fanout {
+    pub fn new(primary: Box<dyn Formatter>) -> Self {
+        Self {
+            formatters: vec![primary],
+        }
+    }
+
+    pub fn with(mut self, report: Box<dyn Formatter>) -> Self {
+        self.formatters.push(report);
+        self
+    }
 }
 
-const GREEN: (u8, u8, u8) = (100, 200, 120);
-const RED: (u8, u8, u8) = (210, 90, 90);
-const YELLOW: (u8, u8, u8) = (200, 180, 80);
+impl Formatter for Fanout {
+    fn metadata(&mut self, catalog: &Catalog) {
+        for f in &mut self.formatters {
+            f.metadata(catalog);
+        }
+    }
+
+    fn wants_test_count(&self) -> bool {
+        self.formatters.iter().any(|f| f.wants_test_count())
+    }
+
+    fn run_started(&mut self, test_count: Option<usize>, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.run_started(test_count, w)?;
+        }
+        Ok(())
+    }
+
+    fn binary_started(&mut self, label: &str, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.binary_started(label, w)?;
+        }
+        Ok(())
+    }
+
+    fn suite_started(&mut self, test_count: usize, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.suite_started(test_count, w)?;
+        }
+        Ok(())
+    }
+
+    fn suite_shuffled(&mut self, seed: u64, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.suite_shuffled(seed, w)?;
+        }
+        Ok(())
+    }
+
+    fn test_started(&mut self, name: &str, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.test_started(name, w)?;
+        }
+        Ok(())
+    }
+
+    fn test_passed(
+        &mut self,
+        name: &str,
+        exec_time: Option<f64>,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.test_passed(name, exec_time, w)?;
+        }
+        Ok(())
+    }
+
+    fn test_output(&mut self, name: &str, stdout: &str, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.test_output(name, stdout, w)?;
+        }
+        Ok(())
+    }
+
+    fn test_failed(
+        &mut self,
+        name: &str,
+        exec_time: Option<f64>,
+        stdout: Option<&str>,
+        message: Option<&str>,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.test_failed(name, exec_time, stdout, message, w)?;
+        }
+        Ok(())
+    }
+
+    fn test_ignored(&mut self, name: &str, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.test_ignored(name, w)?;
+        }
+        Ok(())
+    }
+
+    fn test_ignored_with_message(
+        &mut self,
+        name: &str,
+        message: Option<&str>,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.test_ignored_with_message(name, message, w)?;
+        }
+        Ok(())
+    }
+
+    fn test_allowed_fail(&mut self, name: &str, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.test_allowed_fail(name, w)?;
+        }
+        Ok(())
+    }
+
+    fn test_timeout(&mut self, name: &str, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.test_timeout(name, w)?;
+        }
+        Ok(())
+    }
+
+    fn bench_result(&mut self, bench: &BenchResult, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.bench_result(bench, w)?;
+        }
+        Ok(())
+    }
+
+    fn suite_finished(
+        &mut self,
+        result: &SuiteResult,
+        success: bool,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.suite_finished(result, success, w)?;
+        }
+        Ok(())
+    }
+
+    fn doc_test_times(&mut self, times: &DocTestTimes, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.doc_test_times(times, w)?;
+        }
+        Ok(())
+    }
+
+    fn run_finished(&mut self, summary: &RunSummary, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.run_finished(summary, w)?;
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, text: &str, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.report(text, w)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, w: &mut dyn Write) -> io::Result<()> {
+        for f in &mut self.formatters {
+            f.finish(w)?;
+        }
+        Ok(())
+    }
+}
+
+/// The heading for a test binary's section of the output.
+pub fn write_section(label: Option<&str>, test_count: usize, w: &mut dyn Write) -> io::Result<()> {
+    match label {
+        Some(label) => writeln!(w, "\nRunning {test_count} tests in {label}\n"),
+        None => writeln!(w, "\nRunning {test_count} tests\n"),
+    }
+}
+
+/// The seed a shuffled binary ran with, so the order can be repeated.
+pub fn write_shuffle_seed(seed: u64, w: &mut dyn Write) -> io::Result<()> {
+    writeln!(
+        w,
+        "Shuffled with seed {seed} (repeat with -- --shuffle-seed {seed})\n"
+    )
+}
+
+/// Which shard of the suite ran, for sharded runs.
+pub fn write_shard(summary: &RunSummary, w: &mut dyn Write) -> io::Result<()> {
+    let Some(ref run) = summary.shard else {
+        return Ok(());
+    };
+    writeln!(
+        w,
+        "Shard {}: {} of {} tests\n",
+        run.shard, run.selected, run.total
+    )
+}
+
+/// How many tests didn't run, when `--fail-fast` stopped the run.
+pub fn write_not_run(summary: &RunSummary, w: &mut dyn Write) -> io::Result<()> {
+    let Some(count) = summary.not_run else {
+        return Ok(());
+    };
+    let noun = if count == 1 { "test" } else { "tests" };
+    writeln!(
+        w,
+        "Stopped at the first failure: {count} {noun} didn't run\n"
+    )
+}
+
+/// One line per test binary with its counts and time, when more than one ran.
+///
+/// With `color`, binaries that had failures are labelled in red.
+pub fn write_breakdown(
+    summary: &RunSummary,
+    color: Option<Palette>,
+    w: &mut dyn Write,
+) -> io::Result<()> {
+    if summary.binaries.len() < 2 {
+        return Ok(());
+    }
+    let width = summary
+        .binaries
+        .iter()
+        .map(|b| b.label.chars().count())
+        .max()
+        .unwrap_or(0);
+    for binary in &summary.binaries {
+        let r = &binary.result;
+        let failed = color.filter(|_| r.failed > 0);
+        if let Some(palette) = failed {
+            palette.paint(w, Role::Failure)?;
+        }
+        write!(w, "  {:<width$}", binary.label)?;
+        if failed.is_some() {
+            reset(w)?;
+        }
+        write!(
+            w,
+            "  {} passed, {} failed, {} ignored",
+            r.passed, r.failed, r.ignored
+        )?;
+        if let Some(t) = r.exec_time {
+            write!(w, "  {t:.2}s")?;
+        }
+        writeln!(w)?;
+    }
+    writeln!(w)
+}
 
 /// Write the colored summary line: green passed, red failed, yellow ignored.
-pub fn write_colored_summary(result: &SuiteResult, w: &mut dyn Write) -> io::Result<()> {
-    fg(w, GREEN.0, GREEN.1, GREEN.2)?;
+pub fn write_colored_summary(
+    result: &SuiteResult,
+    palette: Palette,
+    w: &mut dyn Write,
+) -> io::Result<()> {
+    palette.paint(w, Role::Success)?;
     write!(w, "{} passed", result.passed)?;
     reset(w)?;
     write!(w, ", ")?;
-    fg(w, RED.0, RED.1, RED.2)?;
+    palette.paint(w, Role::Failure)?;
     write!(w, "{} failed", result.failed)?;
     reset(w)?;
     write!(w, ", ")?;
-    fg(w, YELLOW.0, YELLOW.1, YELLOW.2)?;
+    palette.paint(w, Role::Warning)?;
     write!(w, "{} ignored", result.ignored)?;
     reset(w)?;
     writeln!(w)?;
     Ok(())
 }
 
-/// Replay captured failures with red coloring.
-pub fn write_colored_failures(failures: &[FailedTest], w: &mut dyn Write) -> io::Result<()> {
+/// The command to re-run each failure, from the target of the binary the
+/// summary recorded it in.
+///
+/// A failure with no binary, or whose binary the summary doesn't hold with
+/// that test failed, as when the binary crashed, gets no command.
+fn rerun_commands(failures: &[FailedTest], summary: &RunSummary) -> Vec<Option<String>> {
+    failures
+        .iter()
+        .map(|fail| {
+            let label = fail.binary.as_deref()?;
+            summary
+                .binaries
+                .iter()
+                .filter(|binary| binary.label == label && binary.failed.contains(&fail.name))
+                .find_map(|binary| binary.target.as_ref())
+                .map(|target| target.rerun_command(&fail.name))
+        })
+        .collect()
+}
+
+/// Replay captured failures, with their titles in red when `color` is given.
+pub fn write_failures(
+    failures: &[FailedTest],
+    summary: &RunSummary,
+    color: Option<Palette>,
+    w: &mut dyn Write,
+) -> io::Result<()> {
     if failures.is_empty() {
         return Ok(());
     }
     writeln!(w, "Failures:\n")?;
-    for (i, fail) in failures.iter().enumerate() {
-        fg(w, RED.0, RED.1, RED.2)?;
-        write!(w, "  {}. {}", i + 1, fail.name)?;
+    let commands = rerun_commands(failures, summary);
+    for (i, (fail, command)) in failures.iter().zip(commands).enumerate() {
+        if let Some(palette) = color {
+            palette.paint(w, Role::Failure)?;
+        }
+        write!(w, "  {}. {}", i + 1, fail.title())?;
         if let Some(t) = fail.exec_time {
             write!(w, " ({t:.2}s)")?;
         }
-        reset(w)?;
+        if color.is_some() {
+            reset(w)?;
+        }
         writeln!(w)?;
 
+        if let Some(origin) = fail.origin() {
+            writeln!(w, "     {origin}")?;
+        }
         if let Some(ref msg) = fail.message {
             writeln!(w, "     {msg}")?;
         }
+        write_snippet(fail, color, w)?;
         if let Some(ref stdout) = fail.stdout {
             let trimmed = stdout.trim();
             if !trimmed.is_empty() {
@@ -111,7 +719,458 @@ pub fn write_colored_failures(failures: &[FailedTest], w: &mut dyn Write) -> io:
                 }
             }
         }
+        if let Some(command) = command {
+            writeln!(w, "     rerun: {command}")?;
+        }
         writeln!(w)?;
     }
     Ok(())
 }
+
+/// Feed `formatter` a run of two binaries, `auth` with a failure and `parse`
+/// with an ignored test, and return what it printed without its colors.
+#[cfg(test)]
+pub(crate) fn run_two_binaries(formatter: &mut dyn Formatter) -> String {
+    use crate::artifacts::Target;
+
+    let mut summary = RunSummary::default();
+    let w = &mut Vec::new();
+
+    formatter.binary_started("auth", w).unwrap();
+    formatter.suite_started(2, w).unwrap();
+    formatter.test_passed("login::works", None, w).unwrap();
+    formatter
+        .test_failed("login::rejects", Some(0.5), None, Some("boom"), w)
+        .unwrap();
+    summary.test_failed("login::rejects");
+    let auth = SuiteResult {
+        passed: 1,
+        failed: 1,
+        exec_time: Some(0.5),
+        ..SuiteResult::default()
+    };
+    formatter.suite_finished(&auth, false, w).unwrap();
+    summary.push("auth", Some(&Target::test("auth")), &auth);
+
+    formatter.binary_started("parse", w).unwrap();
+    formatter.suite_started(1, w).unwrap();
+    formatter.test_ignored("slow", w).unwrap();
+    let parse = SuiteResult {
+        ignored: 1,
+        exec_time: Some(0.25),
+        ..SuiteResult::default()
+    };
+    formatter.suite_finished(&parse, true, w).unwrap();
+    summary.push("parse", Some(&Target::test("parse")), &parse);
+
+    formatter.run_finished(&summary, w).unwrap();
+    crate::diagnostics::strip_ansi(&String::from_utf8_lossy(w))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifacts::Target;
+    use crate::color::{Depth, Theme};
+    use crate::testing::scratch;
+    use std::cell::RefCell;
+    use std::path::Path;
+    use std::rc::Rc;
+
+    const PALETTE: Palette = Palette::with_depth(Theme::Default, Depth::TrueColor);
+
+    /// Notes each event it gets as `<tag> <event>`.
+    struct Recorder {
+        tag: &'static str,
+        log: Rc<RefCell<Vec<String>>>,
+    }
+
+    impl Recorder {
+        fn note(&self, event: String) -> io::Result<()> {
+            self.log.borrow_mut().push(format!("{} {event}", self.tag));
+            Ok(())
+        }
+    }
+
+    impl Formatter for Recorder {
+        fn suite_started(&mut self, test_count: usize, _: &mut dyn Write) -> io::Result<()> {
+            self.note(format!("suite_started {test_count}"))
+        }
+        fn test_started(&mut self, name: &str, _: &mut dyn Write) -> io::Result<()> {
+            self.note(format!("test_started {name}"))
+        }
+        fn test_passed(&mut self, name: &str, _: Option<f64>, _: &mut dyn Write) -> io::Result<()> {
+            self.note(format!("test_passed {name}"))
+        }
+        fn test_failed(
+            &mut self,
+            name: &str,
+            _: Option<f64>,
+            _: Option<&str>,
+            _: Option<&str>,
+            _: &mut dyn Write,
+        ) -> io::Result<()> {
+            self.note(format!("test_failed {name}"))
+        }
+        fn test_ignored(&mut self, name: &str, _: &mut dyn Write) -> io::Result<()> {
+            self.note(format!("test_ignored {name}"))
+        }
+        fn suite_finished(
+            &mut self,
+            _: &SuiteResult,
+            success: bool,
+            _: &mut dyn Write,
+        ) -> io::Result<()> {
+            self.note(format!("suite_finished {success}"))
+        }
+    }
+
+    fn recorder(tag: &'static str, log: &Rc<RefCell<Vec<String>>>) -> Box<dyn Formatter> {
+        Box::new(Recorder {
+            tag,
+            log: Rc::clone(log),
+        })
+    }
+
+    /// A passing and a failing test, as the runner would report them.
+    fn drive(formatter: &mut dyn Formatter) -> String {
+        let mut out = Vec::new();
+        let w: &mut dyn Write = &mut out;
+        formatter.binary_started("auth", w).unwrap();
+        formatter.suite_started(2, w).unwrap();
+        formatter.test_started("passes", w).unwrap();
+        formatter.test_passed("passes", None, w).unwrap();
+        formatter.test_started("fails", w).unwrap();
+        formatter.test_failed("fails", None, None, None, w).unwrap();
+        let result = SuiteResult {
+            passed: 1,
+            failed: 1,
+            ..SuiteResult::default()
+        };
+        formatter.suite_finished(&result, false, w).unwrap();
+        formatter.finish(w).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn registry_holds_the_built_in_formatters() {
+        let registry = Registry::new();
+        let names: Vec<&str> = registry.names().collect();
+        assert_eq!(
+            names,
+            [
+                "pride",
+                "boring",
+                "default",
+                "documentation",
+                "tap",
+                "progress"
+            ]
+        );
+    }
+
+    #[test]
+    fn with_adds_or_replaces_a_formatter() {
+        let log = Rc::default();
+        let shared = Rc::clone(&log);
+        let registry = Registry::new()
+            .with("slack", move |_| recorder("slack", &shared))
+            .with("tap", |_| Box::new(default::DefaultFormatter::new()));
+        let names: Vec<&str> = registry.names().collect();
+        assert_eq!(names.iter().filter(|n| **n == "tap").count(), 1);
+        // A replaced formatter moves to the end
+        assert_eq!(names[names.len() - 2..], ["slack", "tap"]);
+
+        drive(registry.create("slack", Some(PALETTE)).unwrap().as_mut());
+        assert_eq!(log.borrow()[0], "slack suite_started 2");
+    }
+
+    #[test]
+    fn unknown_names_are_an_error() {
+        let Err(e) = Registry::new().create_from("nope", None, OsStr::new("")) else {
+            panic!("nope should not be a formatter");
+        };
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+        assert_eq!(
+            e.to_string(),
+            "unknown formatter nope (no spectacular-formatter-nope on PATH)"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn exec_names_run_the_command() {
+        let dir = scratch("registry-exec");
+        let events = dir.join("events");
+        let name = format!("exec:dd status=none of={}", events.display());
+        drive(Registry::new().create(&name, None).unwrap().as_mut());
+        let lines = std::fs::read_to_string(&events).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let lines: Vec<&str> = lines.lines().collect();
+        assert_eq!(lines[0], r#"{"event":"hello","protocol":1,"color":false}"#);
+        assert!(lines.contains(&r#"{"event":"test_failed","name":"fails","exec_time":null,"stdout":null,"message":null}"#));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn other_names_run_a_plugin_on_the_path() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = scratch("registry-plugin");
+        let plugin = dir.join("spectacular-formatter-capture");
+        std::fs::write(&plugin, "#!/bin/sh\ncat > \"$0.events\"\n").unwrap();
+        std::fs::set_permissions(&plugin, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let path = std::env::join_paths([Path::new("/nonexistent"), &dir]).unwrap();
+
+        drive(
+            Registry::new()
+                .create_from("capture", Some(PALETTE), &path)
+                .unwrap()
+                .as_mut(),
+        );
+        let lines = std::fs::read_to_string(dir.join("spectacular-formatter-capture.events"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let lines = lines.unwrap();
+        assert!(
+            lines.starts_with(r#"{"event":"hello","protocol":1,"color":true}"#),
+            "{lines}"
+        );
+        assert!(
+            lines.contains(r#"{"event":"binary_started","label":"auth"}"#),
+            "{lines}"
+        );
+    }
+
+    #[test]
+    fn fanout_sends_each_event_to_every_formatter_in_turn() {
+        let log = Rc::default();
+        let mut fanout = Fanout::new(recorder("terminal", &log)).with(recorder("junit", &log));
+        drive(&mut fanout);
+        let log = log.borrow();
+        assert_eq!(log.len(), 12);
+        assert_eq!(
+            log[..2],
+            ["terminal suite_started 2", "junit suite_started 2"]
+        );
+        assert_eq!(
+            log[10..],
+            [
+                "terminal suite_finished false",
+                "junit suite_finished false"
+            ]
+        );
+    }
+
+    #[test]
+    fn fanout_wants_a_test_count_when_any_formatter_does() {
+        let log = Rc::default();
+        let fanout = Fanout::new(recorder("terminal", &log)).with(recorder("junit", &log));
+        assert!(!fanout.wants_test_count());
+        let fanout = fanout.with(Box::new(progress::ProgressFormatter::new(None)));
+        assert!(fanout.wants_test_count());
+    }
+
+    #[test]
+    fn fanout_stops_at_the_first_error() {
+        struct Broken;
+        impl Formatter for Broken {
+            fn suite_started(&mut self, _: usize, _: &mut dyn Write) -> io::Result<()> {
+                Err(io::Error::other("broken"))
+            }
+            fn test_started(&mut self, _: &str, _: &mut dyn Write) -> io::Result<()> {
+                Ok(())
+            }
+            fn test_passed(
+                &mut self,
+                _: &str,
+                _: Option<f64>,
+                _: &mut dyn Write,
+            ) -> io::Result<()> {
+                Ok(())
+            }
+            fn test_failed(
+                &mut self,
+                _: &str,
+                _: Option<f64>,
+                _: Option<&str>,
+                _: Option<&str>,
+                _: &mut dyn Write,
+            ) -> io::Result<()> {
+                Ok(())
+            }
+            fn test_ignored(&mut self, _: &str, _: &mut dyn Write) -> io::Result<()> {
+                Ok(())
+            }
+            fn suite_finished(
+                &mut self,
+                _: &SuiteResult,
+                _: bool,
+                _: &mut dyn Write,
+            ) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let log = Rc::default();
+        let mut fanout = Fanout::new(Box::new(Broken)).with(recorder("junit", &log));
+        let err = fanout.suite_started(1, &mut Vec::new()).unwrap_err();
+        assert_eq!(err.to_string(), "broken");
+        assert!(log.borrow().is_empty());
+    }
+
+    fn failure(binary: &str, name: &str) -> FailedTest {
+        FailedTest::new(Some(binary), name, None, None, None, &Catalog::default())
+    }
+
+    #[test]
+    fn panic_location_reads_every_panic_line() {
+        let at = |file: &str, line, column| {
+            Some(PanicLocation {
+                file: file.to_string(),
+                line,
+                column,
+            })
+        };
+        let cases = [
+            (
+                "thread 'auth::rejects' panicked at tests/auth.rs:42:9:\nboom\n",
+                at("tests/auth.rs", 42, 9),
+            ),
+            // Newer toolchains add the thread id
+            (
+                "\nthread 'auth::rejects' (12345) panicked at tests/auth.rs:42:9:\nboom\n",
+                at("tests/auth.rs", 42, 9),
+            ),
+            (
+                "thread 'main' (1) panicked at C:\\work\\src\\lib.rs:3:5:\n",
+                at("C:\\work\\src\\lib.rs", 3, 5),
+            ),
+            // Older toolchains put the message first
+            (
+                "thread 'auth::rejects' panicked at 'boom, really', src/lib.rs:7:1\n",
+                at("src/lib.rs", 7, 1),
+            ),
+            ("checking length\nno panic here\n", None),
+            ("thread 'x' panicked at src/lib.rs:seven:1:\n", None),
+            ("thread 'x' panicked at :7:1:\n", None),
+        ];
+        for (stdout, expected) in cases {
+            assert_eq!(panic_location(stdout), expected, "{stdout:?}");
+        }
+    }
+
+    /// The snippet for a panic at `line` of a ten-line file.
+    fn snippet(line: usize) -> String {
+        let path = scratch(&format!("snippet-{line}")).join("lib.rs");
+        let source: String = (1..=10).map(|n| format!("line {n}\n")).collect();
+        std::fs::write(&path, source).unwrap();
+        let stdout = format!("thread 't' (7) panicked at {}:{line}:5:\n", path.display());
+        let fail = FailedTest::new(None, "t", None, Some(&stdout), None, &Catalog::default());
+        let mut out = Vec::new();
+        write_snippet(&fail, None, &mut out).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    /// The line numbers a snippet shows, and the marked one.
+    fn numbers(snippet: &str) -> (Vec<usize>, Option<usize>) {
+        let mut marked = None;
+        let mut shown = Vec::new();
+        for line in snippet.lines().skip(1) {
+            let (head, _) = line.split_once(" | ").unwrap();
+            let number = head.trim_start_matches([' ', '>']).parse().unwrap();
+            if head.trim_start().starts_with('>') {
+                marked = Some(number);
+            }
+            shown.push(number);
+        }
+        (shown, marked)
+    }
+
+    #[test]
+    fn snippets_show_the_lines_around_the_panic() {
+        assert_eq!(numbers(&snippet(5)), (vec![3, 4, 5, 6, 7], Some(5)));
+        assert!(snippet(5).contains("     > 5 | line 5\n"));
+    }
+
+    #[test]
+    fn snippets_stop_at_the_ends_of_the_file() {
+        assert_eq!(numbers(&snippet(1)), (vec![1, 2, 3], Some(1)));
+        assert_eq!(numbers(&snippet(2)), (vec![1, 2, 3, 4], Some(2)));
+        assert_eq!(numbers(&snippet(10)), (vec![8, 9, 10], Some(10)));
+        // Line numbers are padded to the widest one shown
+        assert!(snippet(10).contains("\n        8 | line 8\n"));
+        assert!(snippet(10).contains("\n     > 10 | line 10\n"));
+    }
+
+    #[test]
+    fn snippets_need_a_line_inside_the_file() {
+        assert_eq!(snippet(11), "");
+        assert_eq!(snippet(0), "");
+    }
+
+    #[test]
+    fn relative_panic_paths_resolve_against_the_workspace_root() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        let mut catalog = Catalog::new(&root);
+        let stdout = "thread 't' (7) panicked at crates/cargo-spectacular/src/lib.rs:1:1:\n";
+        let fail = FailedTest::new(Some("demo"), "t", None, Some(stdout), None, &catalog);
+        assert_eq!(
+            fail.source,
+            Some(root.join("crates/cargo-spectacular/src/lib.rs"))
+        );
+
+        // Then the package of the binary, for a package outside the workspace
+        let stdout = "thread 't' (7) panicked at src/lib.rs:1:1:\n";
+        let here = Path::new(env!("CARGO_MANIFEST_DIR"));
+        catalog.add_package("demo", &here.join("Cargo.toml"));
+        let fail = FailedTest::new(Some("demo"), "t", None, Some(stdout), None, &catalog);
+        assert_eq!(fail.source, Some(here.join("src/lib.rs")));
+        let other = FailedTest::new(Some("other"), "t", None, Some(stdout), None, &catalog);
+        assert_eq!(other.source, None);
+    }
+
+    #[test]
+    fn rerun_commands_skip_a_crashed_binary() {
+        // `a` crashed before its suite ended, so only `b` is in the summary
+        let mut summary = RunSummary::default();
+        summary.test_failed("auth::rejects");
+        let result = SuiteResult {
+            failed: 1,
+            ..SuiteResult::default()
+        };
+        summary.push("b", Some(&Target::test("b")), &result);
+        let failures = [failure("a", "auth::rejects"), failure("b", "auth::rejects")];
+
+        let commands = rerun_commands(&failures, &summary);
+        assert_eq!(
+            commands,
+            [None, Some(Target::test("b").rerun_command("auth::rejects"))]
+        );
+    }
+
+    #[test]
+    fn rerun_commands_follow_the_binary_not_the_order() {
+        let mut summary = RunSummary::default();
+        let result = SuiteResult {
+            failed: 1,
+            ..SuiteResult::default()
+        };
+        summary.test_failed("first");
+        summary.push("a", Some(&Target::test("a")), &result);
+        summary.test_failed("second");
+        summary.push("b", Some(&Target::test("b")), &result);
+        let failures = [failure("b", "second"), failure("a", "first")];
+
+        let commands = rerun_commands(&failures, &summary);
+        assert_eq!(
+            commands,
+            [
+                Some(Target::test("b").rerun_command("second")),
+                Some(Target::test("a").rerun_command("first")),
+            ]
+        );
+    }
+}
@@ -1,5 +1,11 @@
-use super::{FailedTest, Formatter, fg, reset, write_colored_failures, write_colored_summary};
+use super::{
+    FailedTest, Formatter, reset, write_breakdown, write_colored_summary, write_failures,
+    write_not_run, write_section, write_shard, write_shuffle_seed,
+};
+use crate::color::Palette;
 use crate::event::SuiteResult;
+use crate::meta::Catalog;
+use crate::summary::RunSummary;
 use crossterm::terminal;
 use std::f64::consts::TAU;
 use std::io::{self, Write};
@@ -20,15 +26,21 @@ pub struct PrideFormatter {
     dot_count: usize,
     cols: u16,
     failures: Vec<FailedTest>,
+    catalog: Catalog,
+    binary: Option<String>,
+    palette: Palette,
 }
 
 impl PrideFormatter {
-    pub fn new() -> Self {
+    pub fn new(palette: Palette) -> Self {
         let cols = terminal::size().map(|(w, _)| w).unwrap_or(80);
         Self {
             dot_count: 0,
             cols,
             failures: Vec::new(),
+            catalog: Catalog::default(),
+            binary: None,
+            palette,
         }
     }
 
@@ -37,7 +49,7 @@ impl PrideFormatter {
             writeln!(w)?;
         }
         let (r, g, b) = rainbow_color(self.dot_count);
-        fg(w, r, g, b)?;
+        self.palette.fg(w, r, g, b)?;
         write!(w, "{ch}")?;
         reset(w)?;
         w.flush()?;
@@ -48,7 +60,7 @@ impl PrideFormatter {
     fn write_rainbow_text(&self, text: &str, w: &mut dyn Write) -> io::Result<()> {
         for (i, ch) in text.chars().enumerate() {
             let (r, g, b) = rainbow_color(i);
-            fg(w, r, g, b)?;
+            self.palette.fg(w, r, g, b)?;
             write!(w, "{ch}")?;
         }
         reset(w)?;
@@ -57,11 +69,24 @@ impl PrideFormatter {
 }
 
 impl Formatter for PrideFormatter {
-    fn suite_started(&mut self, test_count: usize, w: &mut dyn Write) -> io::Result<()> {
-        writeln!(w, "\nRunning {test_count} tests\n")?;
+    fn metadata(&mut self, catalog: &Catalog) {
+        self.catalog = catalog.clone();
+    }
+
+    fn binary_started(&mut self, label: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.binary = Some(label.to_string());
         Ok(())
     }
 
+    fn suite_started(&mut self, test_count: usize, w: &mut dyn Write) -> io::Result<()> {
+        self.dot_count = 0;
+        write_section(self.binary.as_deref(), test_count, w)
+    }
+
+    fn suite_shuffled(&mut self, seed: u64, w: &mut dyn Write) -> io::Result<()> {
+        write_shuffle_seed(seed, w)
+    }
+
     fn test_started(&mut self, _name: &str, _w: &mut dyn Write) -> io::Result<()> {
         Ok(())
     }
@@ -83,12 +108,14 @@ impl Formatter for PrideFormatter {
         message: Option<&str>,
         w: &mut dyn Write,
     ) -> io::Result<()> {
-        self.failures.push(FailedTest {
-            name: name.to_string(),
+        self.failures.push(FailedTest::new(
+            self.binary.as_deref(),
+            name,
             exec_time,
-            stdout: stdout.map(String::from),
-            message: message.map(String::from),
-        });
+            stdout,
+            message,
+            &self.catalog,
+        ));
         self.emit_dot('X', w)
     }
 
@@ -98,12 +125,19 @@ impl Formatter for PrideFormatter {
 
     fn suite_finished(
         &mut self,
-        result: &SuiteResult,
+        _result: &SuiteResult,
         _success: bool,
         w: &mut dyn Write,
     ) -> io::Result<()> {
-        writeln!(w, "\n")?;
+        // End the line of dots; the summary waits for the last binary
+        self.binary = None;
+        writeln!(w)?;
+        w.flush()
+    }
 
+    fn run_finished(&mut self, summary: &RunSummary, w: &mut dyn Write) -> io::Result<()> {
+        let result = summary.total();
+        writeln!(w)?;
         // "Ran N fabulous tests in X.XXXXs" — rainbow prefix, plain time
         let total = result.passed + result.failed + result.ignored;
         if let Some(t) = result.exec_time {
@@ -118,11 +152,51 @@ impl Formatter for PrideFormatter {
         }
 
         writeln!(w)?;
-        write_colored_summary(result, w)?;
+        write_shard(summary, w)?;
+        write_not_run(summary, w)?;
+        write_breakdown(summary, Some(self.palette), w)?;
+        write_colored_summary(&result, self.palette, w)?;
         writeln!(w)?;
-        write_colored_failures(&self.failures, w)?;
-
+        write_failures(&self.failures, summary, Some(self.palette), w)?;
         w.flush()?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{Depth, Theme};
+    use crate::formatter::run_two_binaries;
+
+    #[test]
+    fn the_summary_adds_up_every_binary() {
+        let palette = Palette::with_depth(Theme::Default, Depth::Ansi16);
+        assert_eq!(
+            run_two_binaries(&mut PrideFormatter::new(palette)),
+            "
+Running 2 tests in auth
+
+.X
+
+Running 1 tests in parse
+
+*
+
+Ran 3 fabulous tests in 0.7500s, 4.0 tests/s
+
+  auth   1 passed, 1 failed, 0 ignored  0.50s
+  parse  0 passed, 0 failed, 1 ignored  0.25s
+
+1 passed, 1 failed, 1 ignored
+
+Failures:
+
+  1. login::rejects (0.50s)
+     boom
+     rerun: cargo test --manifest-path /work/Cargo.toml --test auth -- --exact login::rejects
+
+"
+        );
+    }
+}
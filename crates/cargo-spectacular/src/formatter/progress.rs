@@ -0,0 +1,414 @@
+use super::{
+    FailedTest, Formatter, panic_message, reset, write_breakdown, write_colored_summary,
+    write_failures, write_not_run, write_shard,
+};
+use crate::color::{Palette, Role};
+use crate::event::SuiteResult;
+use crate::meta::Catalog;
+use crate::summary::RunSummary;
+use crossterm::terminal;
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// How often the bar is redrawn at most, so fast suites don't flood the terminal.
+const REDRAW: Duration = Duration::from_millis(50);
+
+/// How often a status line is printed when stdout isn't a terminal.
+const STATUS_EVERY: Duration = Duration::from_secs(5);
+
+/// The narrowest bar worth drawing; below it only the counters are shown.
+const MIN_BAR: usize = 10;
+
+/// One bar for the whole run, redrawn in place, with running counters.
+///
+/// The bar is labelled with the running binary. Failures are printed above
+/// it as they happen and replayed in full after the run. When stdout isn't
+/// a terminal the bar becomes a status line every few seconds.
+pub struct ProgressFormatter {
+    color: Option<Palette>,
+    live: bool,
+    cols: usize,
+    catalog: Catalog,
+    failures: Vec<FailedTest>,
+    binary: Option<String>,
+    /// Tests in the run, and how many of them have finished.
+    total: usize,
+    done: usize,
+    passed: usize,
+    failed: usize,
+    ignored: usize,
+    run_started: Option<Instant>,
+    last_drawn: Option<Instant>,
+}
+
+impl ProgressFormatter {
+    pub fn new(color: Option<Palette>) -> Self {
+        let cols = match terminal::size() {
+            Ok((w, _)) if w > 0 => w,
+            _ => 80,
+        };
+        Self::sized(color, io::stdout().is_terminal(), cols as usize)
+    }
+
+    /// A formatter drawing a bar `cols` wide when `live`, status lines otherwise.
+    fn sized(color: Option<Palette>, live: bool, cols: usize) -> Self {
+        Self {
+            color,
+            live,
+            cols,
+            catalog: Catalog::default(),
+            failures: Vec::new(),
+            binary: None,
+            total: 0,
+            done: 0,
+            passed: 0,
+            failed: 0,
+            ignored: 0,
+            run_started: None,
+            last_drawn: None,
+        }
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.run_started.map(|t| t.elapsed()).unwrap_or_default()
+    }
+
+    /// Time left in the run, from its pace so far.
+    fn eta(&self) -> Option<Duration> {
+        if self.done == 0 || self.done >= self.total {
+            return None;
+        }
+        let per_test = self.elapsed().as_secs_f64() / self.done as f64;
+        Some(Duration::from_secs_f64(
+            per_test * (self.total - self.done) as f64,
+        ))
+    }
+
+    /// A test finished: count it and refresh the bar or status line.
+    fn advance(&mut self, w: &mut dyn Write) -> io::Result<()> {
+        self.done += 1;
+        let due = |every| self.last_drawn.is_none_or(|t| t.elapsed() >= every);
+        if self.live && due(REDRAW) {
+            self.draw_bar(w)?;
+        } else if !self.live && due(STATUS_EVERY) {
+            self.write_status(w)?;
+        }
+        Ok(())
+    }
+
+    /// `auth  [████░░░░]  1200/3000  1198 passed, 2 failed, 0 ignored  12s, ETA 18s`
+    fn draw_bar(&mut self, w: &mut dyn Write) -> io::Result<()> {
+        let label = self.binary.as_deref().unwrap_or("tests");
+        let counts = format!("{}/{}", self.done, self.total);
+        let mut timing = format_duration(self.elapsed());
+        if let Some(eta) = self.eta() {
+            timing.push_str(&format!(", ETA {}", format_duration(eta)));
+        }
+        let counters = format!(
+            "{} passed, {} failed, {} ignored",
+            self.passed, self.failed, self.ignored
+        );
+        let fixed = label.chars().count() + counts.len() + counters.len() + timing.len() + 12;
+        let width = self.cols.saturating_sub(fixed);
+
+        write!(w, "\r\x1b[2K{label}  ")?;
+        if width >= MIN_BAR {
+            let filled = (width * self.done)
+                .checked_div(self.total)
+                .unwrap_or(width)
+                .min(width);
+            let role = if self.failed > 0 {
+                Role::Failure
+            } else {
+                Role::Success
+            };
+            if let Some(palette) = self.color {
+                palette.paint(w, role)?;
+            }
+            write!(w, "[{}{}]", "█".repeat(filled), "░".repeat(width - filled))?;
+            if self.color.is_some() {
+                reset(w)?;
+            }
+            write!(w, "  ")?;
+        }
+        write!(w, "{counts}  ")?;
+        self.write_counters(w)?;
+        write!(w, "  {timing}")?;
+        w.flush()?;
+        self.last_drawn = Some(Instant::now());
+        Ok(())
+    }
+
+    fn write_counters(&self, w: &mut dyn Write) -> io::Result<()> {
+        let counters = [
+            (self.passed, "passed", Role::Success),
+            (self.failed, "failed", Role::Failure),
+            (self.ignored, "ignored", Role::Warning),
+        ];
+        for (i, (count, word, role)) in counters.into_iter().enumerate() {
+            if i > 0 {
+                write!(w, ", ")?;
+            }
+            if let Some(palette) = self.color.filter(|_| count > 0) {
+                palette.paint(w, role)?;
+                write!(w, "{count} {word}")?;
+                reset(w)?;
+            } else {
+                write!(w, "{count} {word}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `auth: 1200/3000 tests, 1198 passed, 2 failed, 0 ignored, 12s elapsed, ETA 18s`
+    fn write_status(&mut self, w: &mut dyn Write) -> io::Result<()> {
+        let label = self.binary.as_deref().unwrap_or("tests");
+        write!(w, "{label}: {}/{} tests, ", self.done, self.total)?;
+        self.write_counters(w)?;
+        write!(w, ", {} elapsed", format_duration(self.elapsed()))?;
+        if let Some(eta) = self.eta() {
+            write!(w, ", ETA {}", format_duration(eta))?;
+        }
+        writeln!(w)?;
+        w.flush()?;
+        self.last_drawn = Some(Instant::now());
+        Ok(())
+    }
+
+    fn clear_bar(&self, w: &mut dyn Write) -> io::Result<()> {
+        if self.live {
+            write!(w, "\r\x1b[2K")?;
+        }
+        Ok(())
+    }
+}
+
+impl Formatter for ProgressFormatter {
+    fn metadata(&mut self, catalog: &Catalog) {
+        self.catalog = catalog.clone();
+    }
+
+    fn wants_test_count(&self) -> bool {
+        true
+    }
+
+    fn run_started(&mut self, test_count: Option<usize>, _w: &mut dyn Write) -> io::Result<()> {
+        self.run_started = Some(Instant::now());
+        self.total = test_count.unwrap_or_default();
+        Ok(())
+    }
+
+    fn binary_started(&mut self, label: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.binary = Some(label.to_string());
+        Ok(())
+    }
+
+    fn suite_started(&mut self, test_count: usize, w: &mut dyn Write) -> io::Result<()> {
+        self.run_started.get_or_insert_with(Instant::now);
+        // Doc-tests aren't in the count the run started with
+        self.total = self.total.max(self.done + test_count);
+        if self.live {
+            self.draw_bar(w)
+        } else {
+            self.last_drawn = Some(Instant::now());
+            Ok(())
+        }
+    }
+
+    fn test_started(&mut self, _name: &str, _w: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn test_passed(
+        &mut self,
+        _name: &str,
+        _exec_time: Option<f64>,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.passed += 1;
+        self.advance(w)
+    }
+
+    fn test_failed(
+        &mut self,
+        name: &str,
+        exec_time: Option<f64>,
+        stdout: Option<&str>,
+        message: Option<&str>,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        let fail = FailedTest::new(
+            self.binary.as_deref(),
+            name,
+            exec_time,
+            stdout,
+            message,
+            &self.catalog,
+        );
+        self.clear_bar(w)?;
+        if let Some(palette) = self.color {
+            palette.paint(w, Role::Failure)?;
+            write!(w, "FAILED")?;
+            reset(w)?;
+        } else {
+            write!(w, "FAILED")?;
+        }
+        writeln!(w, " {}", fail.title())?;
+        let message = message
+            .map(String::from)
+            .or_else(|| stdout.and_then(panic_message));
+        if let Some(message) = message {
+            for line in message.lines() {
+                writeln!(w, "  {line}")?;
+            }
+        }
+        self.failures.push(fail);
+        self.failed += 1;
+        // Put the bar back under the failure straight away
+        self.last_drawn = None;
+        self.advance(w)
+    }
+
+    fn test_ignored(&mut self, _name: &str, w: &mut dyn Write) -> io::Result<()> {
+        self.ignored += 1;
+        self.advance(w)
+    }
+
+    fn suite_finished(
+        &mut self,
+        _result: &SuiteResult,
+        _success: bool,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        if self.live {
+            self.draw_bar(w)?;
+        }
+        w.flush()
+    }
+
+    fn run_finished(&mut self, summary: &RunSummary, w: &mut dyn Write) -> io::Result<()> {
+        let result = summary.total();
+        // Leave the bar where the run ended, for the run as a whole
+        self.binary = None;
+        if self.live {
+            self.draw_bar(w)?;
+            writeln!(w)?;
+        } else {
+            self.write_status(w)?;
+        }
+        writeln!(w)?;
+        write_shard(summary, w)?;
+        write_not_run(summary, w)?;
+        write_breakdown(summary, self.color, w)?;
+        if let Some(palette) = self.color {
+            write_colored_summary(&result, palette, w)?;
+        } else {
+            writeln!(
+                w,
+                "{} passed, {} failed, {} ignored",
+                result.passed, result.failed, result.ignored
+            )?;
+        }
+        if let Some(t) = result.exec_time {
+            writeln!(w, "Finished in {t:.2}s")?;
+        }
+        writeln!(w)?;
+        write_failures(&self.failures, summary, self.color, w)?;
+        w.flush()
+    }
+}
+
+/// `42s`, `3m05s`
+fn format_duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    if secs < 60 {
+        format!("{secs}s")
+    } else {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suite(p: &mut ProgressFormatter, label: &str, passed: usize, w: &mut Vec<u8>) {
+        p.binary_started(label, w).unwrap();
+        p.suite_started(passed, w).unwrap();
+        for i in 0..passed {
+            p.test_passed(&format!("test_{i}"), None, w).unwrap();
+        }
+        let result = SuiteResult {
+            passed,
+            ..SuiteResult::default()
+        };
+        p.suite_finished(&result, true, w).unwrap();
+    }
+
+    #[test]
+    fn one_bar_counts_the_whole_run() {
+        let mut p = ProgressFormatter::sized(None, true, 100);
+        let mut out = Vec::new();
+        p.run_started(Some(5), &mut out).unwrap();
+        suite(&mut p, "auth", 2, &mut out);
+        suite(&mut p, "billing", 3, &mut out);
+        p.run_finished(&RunSummary::default(), &mut out).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let bars: Vec<&str> = out.split("\r\x1b[2K").filter(|s| !s.is_empty()).collect();
+        assert!(
+            bars[0].starts_with("auth  [") && bars[0].contains("  0/5  "),
+            "{out}"
+        );
+        // The second binary carries on from the first, on the same line
+        assert!(
+            bars.iter()
+                .any(|b| b.starts_with("billing") && b.contains("  2/5  ")),
+            "{out}"
+        );
+        assert!(!out.contains("/3  "), "{out}");
+        let last = bars.last().unwrap();
+        assert!(
+            last.starts_with("tests  [") && last.contains("  5/5  "),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn uncounted_suites_grow_the_total() {
+        let mut p = ProgressFormatter::sized(None, false, 80);
+        let mut out = Vec::new();
+        // Two listed tests, then doc-tests the run couldn't count
+        p.run_started(Some(2), &mut out).unwrap();
+        suite(&mut p, "auth", 2, &mut out);
+        suite(&mut p, "auth doc-tests", 3, &mut out);
+        p.run_finished(&RunSummary::default(), &mut out).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(
+            out.starts_with("tests: 5/5 tests, 5 passed, 0 failed, 0 ignored"),
+            "{out}"
+        );
+    }
+
+    #[test]
+    fn eta_follows_the_pace_of_the_whole_run() {
+        let mut p = ProgressFormatter::sized(None, false, 80);
+        p.total = 40;
+        p.done = 10;
+        p.run_started = Instant::now().checked_sub(Duration::from_secs(20));
+        let eta = p.eta().unwrap().as_secs_f64();
+        assert!((60.0..61.0).contains(&eta), "{eta}");
+
+        p.done = 40;
+        assert_eq!(p.eta(), None);
+        p.done = 0;
+        assert_eq!(p.eta(), None);
+    }
+
+    #[test]
+    fn durations_switch_to_minutes() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(185)), "3m05s");
+    }
+}
@@ -0,0 +1,293 @@
+use super::{Formatter, panic_message};
+use crate::event::SuiteResult;
+use crate::meta::Catalog;
+use crate::summary::RunSummary;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::time::Instant;
+
+/// TAP version 14 output for prove-style consumers.
+///
+/// Each test binary is a subtest with its own plan; the top-level stream has
+/// one point per binary and a trailing plan once the run finishes.
+pub struct TapFormatter {
+    catalog: Catalog,
+    started: bool,
+    /// When each running test started, for durations libtest doesn't report.
+    running: HashMap<String, Instant>,
+    binary: Option<String>,
+    /// Test points written in the current subtest, of the `planned` ones.
+    count: usize,
+    planned: usize,
+    /// Subtests (binaries) finished so far.
+    suites: usize,
+}
+
+impl TapFormatter {
+    pub fn new() -> Self {
+        Self {
+            catalog: Catalog::default(),
+            started: false,
+            running: HashMap::new(),
+            binary: None,
+            count: 0,
+            planned: 0,
+            suites: 0,
+        }
+    }
+
+    fn point(
+        &mut self,
+        ok: bool,
+        name: &str,
+        directive: &str,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.count += 1;
+        let status = if ok { "ok" } else { "not ok" };
+        let description = self
+            .catalog
+            .description(self.binary.as_deref(), name)
+            .unwrap_or_else(|| name.to_string());
+        writeln!(
+            w,
+            "    {status} {} - {}{directive}",
+            self.count,
+            escape(&description)
+        )
+    }
+}
+
+/// Escape `#` and `\` so a description can't start a directive.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('#', "\\#")
+}
+
+/// Write a YAML block scalar, indented under `key`.
+fn write_block(key: &str, text: &str, w: &mut dyn Write) -> io::Result<()> {
+    writeln!(w, "      {key}: |")?;
+    for line in text.trim_end().lines() {
+        writeln!(w, "        {line}")?;
+    }
+    Ok(())
+}
+
+impl Default for TapFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Formatter for TapFormatter {
+    fn metadata(&mut self, catalog: &Catalog) {
+        self.catalog = catalog.clone();
+    }
+
+    fn binary_started(&mut self, label: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.binary = Some(label.to_string());
+        Ok(())
+    }
+
+    fn suite_started(&mut self, test_count: usize, w: &mut dyn Write) -> io::Result<()> {
+        if !self.started {
+            writeln!(w, "TAP version 14")?;
+            self.started = true;
+        }
+        let name = self
+            .binary
+            .clone()
+            .unwrap_or_else(|| format!("suite {}", self.suites + 1));
+        writeln!(w, "# Subtest: {name}")?;
+        writeln!(w, "    1..{test_count}")?;
+        self.count = 0;
+        self.planned = test_count;
+        w.flush()
+    }
+
+    fn suite_shuffled(&mut self, seed: u64, w: &mut dyn Write) -> io::Result<()> {
+        writeln!(w, "    # shuffled with seed {seed}")
+    }
+
+    fn test_started(&mut self, name: &str, _w: &mut dyn Write) -> io::Result<()> {
+        self.running.insert(name.to_string(), Instant::now());
+        Ok(())
+    }
+
+    fn test_passed(
+        &mut self,
+        name: &str,
+        _exec_time: Option<f64>,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.running.remove(name);
+        self.point(true, name, "", w)?;
+        w.flush()
+    }
+
+    fn test_failed(
+        &mut self,
+        name: &str,
+        exec_time: Option<f64>,
+        stdout: Option<&str>,
+        message: Option<&str>,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.point(false, name, "", w)?;
+
+        writeln!(w, "      ---")?;
+        let message = message
+            .map(String::from)
+            .or_else(|| stdout.and_then(panic_message));
+        if let Some(message) = message {
+            write_block("message", &message, w)?;
+        }
+        if let Some(stdout) = stdout
+            && !stdout.trim().is_empty()
+        {
+            write_block("stdout", stdout, w)?;
+        }
+        let measured = self
+            .running
+            .remove(name)
+            .map(|start| start.elapsed().as_secs_f64());
+        if let Some(t) = exec_time.or(measured) {
+            writeln!(w, "      duration_ms: {:.0}", t * 1000.0)?;
+        }
+        if let Some(loc) = self.catalog.location(self.binary.as_deref(), name) {
+            writeln!(w, "      at: {loc}")?;
+        }
+        writeln!(w, "      ...")?;
+        w.flush()
+    }
+
+    fn test_ignored(&mut self, name: &str, w: &mut dyn Write) -> io::Result<()> {
+        self.test_ignored_with_message(name, None, w)
+    }
+
+    fn test_ignored_with_message(
+        &mut self,
+        name: &str,
+        message: Option<&str>,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        self.running.remove(name);
+        let directive = match message {
+            Some(reason) => format!(" # SKIP {}", escape(reason)),
+            None => String::from(" # SKIP"),
+        };
+        self.point(true, name, &directive, w)?;
+        w.flush()
+    }
+
+    fn suite_finished(
+        &mut self,
+        result: &SuiteResult,
+        success: bool,
+        w: &mut dyn Write,
+    ) -> io::Result<()> {
+        // Tests `--fail-fast` stopped before they ran still need their points
+        // for the subtest to match its plan; benchmarks aren't points
+        while self.count + result.measured < self.planned {
+            self.count += 1;
+            writeln!(w, "    ok {} # SKIP not run", self.count)?;
+        }
+        self.suites += 1;
+        let status = if success { "ok" } else { "not ok" };
+        let name = self
+            .binary
+            .take()
+            .unwrap_or_else(|| format!("suite {}", self.suites));
+        writeln!(w, "{status} {} - {}", self.suites, escape(&name))?;
+        w.flush()
+    }
+
+    fn run_finished(&mut self, summary: &RunSummary, w: &mut dyn Write) -> io::Result<()> {
+        if !self.started {
+            writeln!(w, "TAP version 14")?;
+        }
+        if let Some(ref run) = summary.shard {
+            writeln!(
+                w,
+                "# Shard {}: {} of {} tests",
+                run.shard, run.selected, run.total
+            )?;
+        }
+        if let Some(count) = summary.not_run {
+            writeln!(
+                w,
+                "# Stopped at the first failure: {count} tests didn't run"
+            )?;
+        }
+        writeln!(w, "1..{}", self.suites)?;
+        w.flush()
+    }
+
+    /// Reports become comments, so the stream stays valid TAP.
+    fn report(&mut self, text: &str, w: &mut dyn Write) -> io::Result<()> {
+        for line in text.trim_end().lines() {
+            if line.is_empty() {
+                writeln!(w, "#")?;
+            } else {
+                writeln!(w, "# {line}")?;
+            }
+        }
+        w.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(f: impl FnOnce(&mut TapFormatter, &mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut tap = TapFormatter::new();
+        let mut out = Vec::new();
+        f(&mut tap, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn stopped_suite_still_matches_its_plan() {
+        let out = output(|tap, w| {
+            tap.binary_started("auth", w)?;
+            tap.suite_started(3, w)?;
+            tap.test_failed("rejects", None, None, Some("boom"), w)?;
+            let partial = SuiteResult {
+                failed: 1,
+                ..SuiteResult::default()
+            };
+            tap.suite_finished(&partial, false, w)?;
+            let mut summary = RunSummary::default();
+            summary.stop(2);
+            tap.run_finished(&summary, w)
+        });
+
+        assert!(out.contains("    1..3\n    not ok 1 - rejects\n"), "{out}");
+        assert!(
+            out.contains("    ok 2 # SKIP not run\n    ok 3 # SKIP not run\nnot ok 1 - auth\n"),
+            "{out}"
+        );
+        assert!(out.ends_with("# Stopped at the first failure: 2 tests didn't run\n1..1\n"));
+    }
+
+    #[test]
+    fn benchmarks_are_not_padded() {
+        let out = output(|tap, w| {
+            tap.suite_started(2, w)?;
+            tap.test_passed("works", None, w)?;
+            let result = SuiteResult {
+                passed: 1,
+                measured: 1,
+                ..SuiteResult::default()
+            };
+            tap.suite_finished(&result, true, w)
+        });
+
+        assert!(!out.contains("SKIP"), "{out}");
+    }
+
+    #[test]
+    fn descriptions_cannot_start_a_directive() {
+        assert_eq!(escape(r"issue #12 \o/"), r"issue \#12 \\o/");
+    }
+}
@@ -0,0 +1,238 @@
+use crate::artifacts::Target;
+use crate::select;
+use crate::summary::RunSummary;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// A test is slower than usual when it takes this many times its median.
+pub const DEFAULT_FACTOR: f64 = 2.0;
+
+/// Durations kept per test; older ones roll off.
+const KEEP: usize = 20;
+
+/// Runs a test needs in its history before it can be called slow.
+const MIN_SAMPLES: usize = 5;
+
+/// Slowdowns smaller than this are timer noise, however large the factor.
+const MIN_SLOWDOWN: f64 = 0.02;
+
+/// Recent durations of every test in one target, in seconds, oldest first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TargetHistory {
+    tests: BTreeMap<String, Vec<f64>>,
+}
+
+/// A test that took much longer than it usually does.
+pub struct Slowdown {
+    /// The binary's label, as in the summary.
+    pub label: String,
+    pub name: String,
+    pub time: f64,
+    pub median: f64,
+}
+
+/// The cargo profile a run's tests were built in, which keeps apart
+/// histories that can't be compared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// Plain `cargo test` runs, in the `test` profile.
+    Test,
+    /// `--bench` runs, built with `--release`.
+    Release,
+}
+
+impl Profile {
+    fn dir_name(self) -> &'static str {
+        match self {
+            Profile::Test => "test",
+            Profile::Release => "release",
+        }
+    }
+}
+
+/// Compare this run's durations with the history in `dir` for `profile`,
+/// then add them to it.
+///
+/// Returns the tests that took more than `factor` times their median. Only
+/// passing tests are recorded: a failure's duration says little about the
+/// test's usual speed. Binaries whose target is unknown are skipped.
+pub fn record(
+    dir: &Path,
+    profile: Profile,
+    summary: &RunSummary,
+    factor: f64,
+) -> io::Result<Vec<Slowdown>> {
+    let dir = dir.join("history").join(profile.dir_name());
+    let mut slowdowns = Vec::new();
+
+    for binary in &summary.binaries {
+        let Some(ref target) = binary.target else {
+            continue;
+        };
+        let passed: Vec<&(String, f64)> = binary
+            .durations
+            .iter()
+            .filter(|(name, _)| !binary.failed.contains(name))
+            .collect();
+        if passed.is_empty() {
+            continue;
+        }
+
+        let path = dir.join(file_name(target));
+        let mut history = load(&path)?;
+        for (name, time) in passed {
+            let samples = history.tests.entry(name.clone()).or_default();
+            if samples.len() >= MIN_SAMPLES {
+                let median = median(samples);
+                if *time > median * factor && *time - median >= MIN_SLOWDOWN {
+                    slowdowns.push(Slowdown {
+                        label: binary.label.clone(),
+                        name: name.clone(),
+                        time: *time,
+                        median,
+                    });
+                }
+            }
+            samples.push(*time);
+            if samples.len() > KEEP {
+                samples.drain(..samples.len() - KEEP);
+            }
+        }
+
+        fs::create_dir_all(&dir)?;
+        fs::write(&path, serde_json::to_string(&history)? + "\n")?;
+    }
+    Ok(slowdowns)
+}
+
+/// The "slower than usual" section of the summary, when there is anything in it.
+pub fn write_slowdowns(slowdowns: &[Slowdown], factor: f64, w: &mut dyn Write) -> io::Result<()> {
+    if slowdowns.is_empty() {
+        return Ok(());
+    }
+    writeln!(w, "Slower than usual (more than {factor}x their median):")?;
+    for slow in slowdowns {
+        writeln!(
+            w,
+            "  {:>8.3}s  {:>4.1}x median {:.3}s  {}  {}",
+            slow.time,
+            slow.time / slow.median,
+            slow.median,
+            slow.label,
+            slow.name
+        )?;
+    }
+    writeln!(w)?;
+    w.flush()
+}
+
+fn load(path: &Path) -> io::Result<TargetHistory> {
+    match fs::read_to_string(path) {
+        // A damaged history is started over rather than failing the run
+        Ok(json) => Ok(serde_json::from_str(&json).unwrap_or_default()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(TargetHistory::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// `<kind>-<name>-<hash>.json`, where the hash of the manifest path tells
+/// apart same-named targets of different packages.
+fn file_name(target: &Target) -> String {
+    let hash = select::stable_hash(&target.manifest_path);
+    format!("{}-{}-{hash:016x}.json", target.kind.as_str(), target.name)
+}
+
+fn median(samples: &[f64]) -> f64 {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::SuiteResult;
+    use crate::testing::scratch;
+
+    /// A run of the `auth` target where `login` took `time` and `logout` failed.
+    fn run(time: f64) -> RunSummary {
+        let mut summary = RunSummary::default();
+        summary.test_timed("login", time);
+        summary.test_timed("logout", 9.0);
+        summary.test_failed("logout");
+        summary.push("auth", Some(&Target::test("auth")), &SuiteResult::default());
+        summary
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(median(&[3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&[4.0, 1.0, 3.0, 2.0]), 2.5);
+        assert_eq!(median(&[0.5]), 0.5);
+    }
+
+    #[test]
+    fn a_test_far_over_its_median_is_slow() {
+        let dir = scratch("history-slow");
+        let mut found = Vec::new();
+        for time in [0.10, 0.11, 0.09, 0.10, 0.10, 0.35] {
+            found = record(&dir, Profile::Test, &run(time), DEFAULT_FACTOR).unwrap();
+        }
+        let again = record(&dir, Profile::Test, &run(0.12), DEFAULT_FACTOR).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            (found[0].label.as_str(), found[0].name.as_str()),
+            ("auth", "login")
+        );
+        assert_eq!(found[0].time, 0.35);
+        assert_eq!(found[0].median, 0.10);
+        assert!(again.is_empty());
+    }
+
+    #[test]
+    fn failures_and_short_histories_are_not_slow() {
+        let dir = scratch("history-short");
+        let mut found = Vec::new();
+        for time in [0.10, 0.10, 0.10, 0.10, 0.90] {
+            found.extend(record(&dir, Profile::Test, &run(time), DEFAULT_FACTOR).unwrap());
+        }
+        let history = load(
+            &dir.join("history/test")
+                .join(file_name(&Target::test("auth"))),
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Four samples aren't enough to judge the fifth
+        assert!(found.is_empty());
+        let history = history.unwrap();
+        assert_eq!(history.tests["login"].len(), 5);
+        assert!(!history.tests.contains_key("logout"));
+    }
+
+    #[test]
+    fn release_runs_keep_their_own_history() {
+        let dir = scratch("history-profiles");
+        for _ in 0..5 {
+            record(&dir, Profile::Test, &run(1.0), DEFAULT_FACTOR).unwrap();
+            record(&dir, Profile::Release, &run(0.05), DEFAULT_FACTOR).unwrap();
+        }
+        let test = record(&dir, Profile::Test, &run(1.1), DEFAULT_FACTOR).unwrap();
+        let release = record(&dir, Profile::Release, &run(1.0), DEFAULT_FACTOR).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        // The fast release runs don't pull the test profile's median down
+        assert!(test.is_empty());
+        assert_eq!(release.len(), 1);
+        assert_eq!(release[0].median, 0.05);
+    }
+}
@@ -0,0 +1,510 @@
+use crate::event::{BenchResult, Event, SuiteEvent, SuiteResult, TestEvent};
+
+/// Turns libtest's default (pretty) output into the JSON backend's events.
+///
+/// Stable toolchains can't ask libtest for JSON, so the runner reads what a
+/// plain `cargo test` prints instead. Failed tests are reported when their
+/// suite ends, because their captured output is only printed after every
+/// test has run, unless the run stops at the first failure. libtest doesn't
+/// print start times, so there are no `started` events and durations stay
+/// unknown.
+#[derive(Debug, Default)]
+pub struct HumanParser {
+    /// Report failures as soon as they happen, without their captured output.
+    fail_fast: bool,
+    state: State,
+    /// Failed tests, in the order they failed, with their captured output.
+    failures: Vec<(String, Option<String>)>,
+    /// The failure whose `---- <name> stdout ----` section is being read.
+    capturing: Option<usize>,
+    /// Tests that passed, were ignored or measured so far, for a suite that
+    /// ends without its `test result:` line.
+    seen: SuiteResult,
+}
+
+#[derive(Debug, Default, PartialEq)]
+enum State {
+    /// Between suites: nothing here belongs to libtest.
+    #[default]
+    Idle,
+    /// Reading `test <name> ... <result>` lines.
+    Running,
+    /// Reading the captured output of failed tests.
+    Failures,
+    /// Reading the final list of failed test names.
+    FailureList,
+}
+
+impl HumanParser {
+    /// A parser for a run that stops at the first failure: failed tests are
+    /// reported on their `... FAILED` line, so the run can stop there, and
+    /// their captured output is never seen.
+    pub fn fail_fast() -> Self {
+        Self {
+            fail_fast: true,
+            ..Self::default()
+        }
+    }
+
+    /// Start over between suites, in the same mode.
+    fn reset(&mut self) {
+        *self = Self {
+            fail_fast: self.fail_fast,
+            ..Self::default()
+        };
+    }
+
+    /// Feed one line of output.
+    ///
+    /// Returns the events it completes, or `None` when the line isn't libtest's
+    /// and should be shown as is.
+    pub fn feed(&mut self, line: &str) -> Option<Vec<Event>> {
+        if self.state != State::Failures
+            && let Some(count) = line.strip_prefix("running ")
+        {
+            // `running 2 tests (shuffle seed: 123)` under `--shuffle`
+            let (count, shuffle_seed) = match count.split_once(" (shuffle seed: ") {
+                Some((count, seed)) => (count, seed.strip_suffix(')')?.parse().ok()),
+                None => (count, None),
+            };
+            let count = count.trim_end_matches(['s', ' ']).strip_suffix(" test")?;
+            let test_count = count.parse().ok()?;
+            self.reset();
+            self.state = State::Running;
+            return Some(vec![Event::Suite(SuiteEvent::Started {
+                test_count,
+                shuffle_seed,
+            })]);
+        }
+        if self.state == State::Idle {
+            return None;
+        }
+        if let Some(result) = line.strip_prefix("test result: ") {
+            return Some(self.finish(result));
+        }
+
+        match self.state {
+            State::Running => {
+                if line == "failures:" {
+                    self.state = State::Failures;
+                    return Some(Vec::new());
+                }
+                if line.is_empty() {
+                    return Some(Vec::new());
+                }
+                self.test_line(line)
+            }
+            State::Failures => {
+                if line == "failures:" {
+                    self.state = State::FailureList;
+                } else if let Some(name) = line
+                    .strip_prefix("---- ")
+                    .and_then(|l| l.strip_suffix(" stdout ----"))
+                {
+                    self.capturing = self.failures.iter().position(|(n, _)| n == name);
+                } else if let Some(i) = self.capturing {
+                    let stdout = self.failures[i].1.get_or_insert_with(String::new);
+                    stdout.push_str(line);
+                    stdout.push('\n');
+                }
+                Some(Vec::new())
+            }
+            State::FailureList | State::Idle => Some(Vec::new()),
+        }
+    }
+
+    /// Parse `test <name> ... <result>`, where the result may be a
+    /// benchmark's measurement, or libtest's warning that a test
+    /// has been running for over 60 seconds. Failures are held back until the
+    /// suite ends, unless the run stops at the first one.
+    fn test_line(&mut self, line: &str) -> Option<Vec<Event>> {
+        if let Some(name) = line
+            .strip_prefix("test ")
+            .and_then(|l| l.strip_suffix(" has been running for over 60 seconds"))
+        {
+            let name = name.to_string();
+            return Some(vec![Event::Test(TestEvent::Timeout { name })]);
+        }
+        let (name, result) = line.strip_prefix("test ")?.rsplit_once(" ... ")?;
+        let name = [" - should panic", " - compile fail", " - compile"]
+            .iter()
+            .find_map(|mode| name.strip_suffix(mode))
+            .unwrap_or(name)
+            .trim_end()
+            .to_string();
+        let (result, exec_time) = match result.split_once(" <") {
+            Some((result, time)) => (result, parse_time(time.trim_end_matches('>'))),
+            None => (result, None),
+        };
+
+        let event = match result {
+            "ok" => {
+                self.seen.passed += 1;
+                Event::Test(TestEvent::Ok {
+                    name,
+                    exec_time,
+                    stdout: None,
+                })
+            }
+            "FAILED" if self.fail_fast => {
+                self.seen.failed += 1;
+                Event::Test(TestEvent::Failed {
+                    name,
+                    exec_time,
+                    stdout: None,
+                    message: None,
+                    reason: None,
+                })
+            }
+            "FAILED" => {
+                self.failures.push((name, None));
+                return Some(Vec::new());
+            }
+            "ignored" => {
+                self.seen.ignored += 1;
+                Event::Test(TestEvent::Ignored {
+                    name,
+                    message: None,
+                })
+            }
+            r if r.starts_with("ignored, ") => {
+                self.seen.ignored += 1;
+                Event::Test(TestEvent::Ignored {
+                    name,
+                    message: Some(r["ignored, ".len()..].to_string()),
+                })
+            }
+            r if r.starts_with("bench:") => {
+                self.seen.measured += 1;
+                Event::Bench(parse_bench(name, r)?)
+            }
+            _ => return None,
+        };
+        Some(vec![event])
+    }
+
+    /// End a suite whose binary stopped before its `test result:` line, as
+    /// when a test aborts the process: release the held-back failures, then
+    /// fail the suite with what it reported so far.
+    ///
+    /// Between suites there is nothing to end.
+    pub fn abort(&mut self) -> Vec<Event> {
+        if self.state == State::Idle {
+            return Vec::new();
+        }
+        let mut suite = std::mem::take(&mut self.seen);
+        suite.failed += self.failures.len();
+        let mut events = self.release_failures();
+        events.push(Event::Suite(SuiteEvent::Failed(suite)));
+        self.reset();
+        events
+    }
+
+    /// The held-back failures, with whatever output was captured for them.
+    fn release_failures(&mut self) -> Vec<Event> {
+        std::mem::take(&mut self.failures)
+            .into_iter()
+            .map(|(name, stdout)| {
+                let stdout = stdout.map(|s| format!("{}\n", s.trim_end()));
+                Event::Test(TestEvent::Failed {
+                    name,
+                    exec_time: None,
+                    stdout,
+                    message: None,
+                    reason: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Parse the `test result:` line: release the held-back failures, then end the suite.
+    fn finish(&mut self, result: &str) -> Vec<Event> {
+        let mut events = self.release_failures();
+
+        let (status, counts) = result.split_once(". ").unwrap_or((result, ""));
+        let mut suite = SuiteResult::default();
+        for part in counts.split("; ") {
+            match part.split_once(' ') {
+                Some((n, "passed")) => suite.passed = n.parse().unwrap_or(0),
+                Some((n, "failed")) => suite.failed = n.parse().unwrap_or(0),
+                Some((n, "ignored")) => suite.ignored = n.parse().unwrap_or(0),
+                Some((n, "measured")) => suite.measured = n.parse().unwrap_or(0),
+                Some((n, "filtered out")) => suite.filtered_out = n.parse().unwrap_or(0),
+                Some(("finished", time)) => {
+                    suite.exec_time = time.strip_prefix("in ").and_then(parse_time);
+                }
+                _ => {}
+            }
+        }
+
+        events.push(Event::Suite(if status == "ok" {
+            SuiteEvent::Ok(suite)
+        } else {
+            SuiteEvent::Failed(suite)
+        }));
+        self.reset();
+        events
+    }
+}
+
+/// Parse a benchmark result: `bench:  1,234.50 ns/iter (+/- 56.00) = 830 MB/s`.
+fn parse_bench(name: String, result: &str) -> Option<BenchResult> {
+    let (median, rest) = result
+        .strip_prefix("bench:")?
+        .split_once(" ns/iter (+/- ")?;
+    let (deviation, rest) = rest.split_once(')')?;
+    let number = |s: &str| s.trim().replace(',', "").parse::<f64>().ok();
+    let mib_per_second = rest
+        .trim()
+        .strip_prefix("= ")
+        .and_then(|s| s.strip_suffix(" MB/s"))
+        .and_then(|s| s.replace(',', "").parse().ok());
+    Some(BenchResult {
+        name,
+        median: number(median)?,
+        deviation: number(deviation)?,
+        mib_per_second,
+    })
+}
+
+/// Parse a libtest duration such as `0.32s`.
+fn parse_time(s: &str) -> Option<f64> {
+    s.trim().strip_suffix('s')?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// libtest's output of a binary with a failure, ignore reasons and `should_panic`.
+    const FAILURES: &str = "\
+running 5 tests
+test tests::fails ... FAILED
+test tests::needs_db ... ignored, needs a database
+test tests::panics - should panic ... ok
+test tests::passes ... ok
+test tests::plain_ignored ... ignored
+
+failures:
+
+---- tests::fails stdout ----
+some output
+
+thread 'tests::fails' (20995) panicked at src/lib.rs:17:9:
+assertion `left == right` failed: numbers differ
+  left: 1
+ right: 2
+note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace
+
+
+failures:
+    tests::fails
+
+test result: FAILED. 2 passed; 1 failed; 2 ignored; 0 measured; 0 filtered out; finished in 0.00s
+";
+
+    const SHUFFLED: &str = "\
+running 2 tests (shuffle seed: 7)
+test tests::plain_ignored ... ignored
+test tests::passes ... ok
+
+test result: ok. 1 passed; 0 failed; 1 ignored; 0 measured; 3 filtered out; finished in 0.01s
+";
+
+    const BENCHES: &str = "\
+running 2 tests
+test tests::copies ... bench:          12.92 ns/iter (+/- 1.41) = 85333 MB/s
+test tests::sums   ... bench:       1,000.25 ns/iter (+/- 0.01)
+
+test result: ok. 0 passed; 0 failed; 0 ignored; 2 measured; 0 filtered out; finished in 1.20s
+";
+
+    const TIMEOUT: &str = "\
+running 1 test
+test tests::slow has been running for over 60 seconds
+test tests::slow ... ok
+
+test result: ok. 1 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 61.02s
+";
+
+    /// A test called `std::process::abort`: cargo's error follows on the same line.
+    const ABORTED: &str = "\
+running 4 tests
+test tests::quick ... ok
+test tests::fails ... FAILED
+test tests::aborts ... error: test failed, to rerun pass `--lib`
+
+Caused by:
+  process didn't exit successfully: `target/debug/deps/hb-791bd4374d676e71` (signal: 6, SIGABRT: process abort signal)
+";
+
+    /// One event per line, compact enough to compare.
+    fn describe(event: &Event) -> String {
+        match event {
+            Event::Suite(SuiteEvent::Started {
+                test_count,
+                shuffle_seed,
+            }) => format!("started {test_count} seed {shuffle_seed:?}"),
+            Event::Suite(SuiteEvent::Ok(r)) | Event::Suite(SuiteEvent::Failed(r)) => {
+                let status = match event {
+                    Event::Suite(SuiteEvent::Ok(_)) => "ok",
+                    _ => "failed",
+                };
+                format!(
+                    "suite {status} {} passed {} failed {} ignored {} measured {} filtered in {:?}",
+                    r.passed, r.failed, r.ignored, r.measured, r.filtered_out, r.exec_time
+                )
+            }
+            Event::Test(TestEvent::Ok { name, .. }) => format!("ok {name}"),
+            Event::Test(TestEvent::Failed { name, .. }) => format!("failed {name}"),
+            Event::Test(TestEvent::Ignored { name, message }) => {
+                format!("ignored {name} {message:?}")
+            }
+            Event::Test(TestEvent::Timeout { name }) => format!("timeout {name}"),
+            Event::Test(other) => format!("{other:?}"),
+            Event::Bench(b) => format!(
+                "bench {} {} ± {} {:?}",
+                b.name, b.median, b.deviation, b.mib_per_second
+            ),
+            Event::Report(_) => String::from("report"),
+        }
+    }
+
+    /// Feed `output` line by line: the events, and the lines left for stderr.
+    fn parse(parser: &mut HumanParser, output: &str) -> (Vec<Event>, Vec<String>) {
+        let mut events = Vec::new();
+        let mut passed_on = Vec::new();
+        for line in output.lines() {
+            match parser.feed(line) {
+                Some(more) => events.extend(more),
+                None => passed_on.push(line.to_string()),
+            }
+        }
+        (events, passed_on)
+    }
+
+    #[test]
+    fn reads_captured_output() {
+        let cases: &[(&str, &str, &[&str])] = &[
+            (
+                "failures",
+                FAILURES,
+                &[
+                    "started 5 seed None",
+                    "ignored tests::needs_db Some(\"needs a database\")",
+                    "ok tests::panics",
+                    "ok tests::passes",
+                    "ignored tests::plain_ignored None",
+                    "failed tests::fails",
+                    "suite failed 2 passed 1 failed 2 ignored 0 measured 0 filtered in Some(0.0)",
+                ],
+            ),
+            (
+                "shuffle seed",
+                SHUFFLED,
+                &[
+                    "started 2 seed Some(7)",
+                    "ignored tests::plain_ignored None",
+                    "ok tests::passes",
+                    "suite ok 1 passed 0 failed 1 ignored 0 measured 3 filtered in Some(0.01)",
+                ],
+            ),
+            (
+                "benchmarks",
+                BENCHES,
+                &[
+                    "started 2 seed None",
+                    "bench tests::copies 12.92 ± 1.41 Some(85333)",
+                    "bench tests::sums 1000.25 ± 0.01 None",
+                    "suite ok 0 passed 0 failed 0 ignored 2 measured 0 filtered in Some(1.2)",
+                ],
+            ),
+            (
+                "timeout",
+                TIMEOUT,
+                &[
+                    "started 1 seed None",
+                    "timeout tests::slow",
+                    "ok tests::slow",
+                    "suite ok 1 passed 0 failed 0 ignored 0 measured 0 filtered in Some(61.02)",
+                ],
+            ),
+        ];
+
+        for (case, output, expected) in cases {
+            let mut parser = HumanParser::default();
+            let (events, passed_on) = parse(&mut parser, output);
+            let events: Vec<String> = events.iter().map(describe).collect();
+            assert_eq!(events, *expected, "{case}");
+            assert!(passed_on.is_empty(), "{case}: {passed_on:?}");
+            assert!(parser.abort().is_empty(), "{case}: the suite ended");
+        }
+    }
+
+    #[test]
+    fn failure_keeps_its_captured_output() {
+        let mut parser = HumanParser::default();
+        let (events, _) = parse(&mut parser, FAILURES);
+        let stdout = events.iter().find_map(|e| match e {
+            Event::Test(TestEvent::Failed { stdout, .. }) => stdout.clone(),
+            _ => None,
+        });
+
+        let stdout = stdout.expect("captured output");
+        assert!(stdout.starts_with("some output\n\nthread 'tests::fails'"));
+        assert!(stdout.ends_with("display a backtrace\n"), "{stdout:?}");
+    }
+
+    #[test]
+    fn aborted_binary_ends_its_suite() {
+        let mut parser = HumanParser::default();
+        let (events, passed_on) = parse(&mut parser, ABORTED);
+        let events: Vec<String> = events.iter().map(describe).collect();
+        assert_eq!(events, ["started 4 seed None", "ok tests::quick"]);
+        // cargo's error is left for stderr
+        assert_eq!(passed_on.len(), 3, "{passed_on:?}");
+
+        let ended: Vec<String> = parser.abort().iter().map(describe).collect();
+        assert_eq!(
+            ended,
+            [
+                "failed tests::fails",
+                "suite failed 1 passed 1 failed 0 ignored 0 measured 0 filtered in None",
+            ]
+        );
+        assert!(parser.abort().is_empty());
+    }
+
+    #[test]
+    fn fail_fast_reports_failures_right_away() {
+        let mut parser = HumanParser::fail_fast();
+        let output = "running 3 tests\ntest tests::passes ... ok\ntest tests::fails ... FAILED";
+        let (events, _) = parse(&mut parser, output);
+        let events: Vec<String> = events.iter().map(describe).collect();
+        assert_eq!(
+            events,
+            [
+                "started 3 seed None",
+                "ok tests::passes",
+                "failed tests::fails"
+            ]
+        );
+
+        let ended: Vec<String> = parser.abort().iter().map(describe).collect();
+        assert_eq!(
+            ended,
+            ["suite failed 1 passed 1 failed 0 ignored 0 measured 0 filtered in None"]
+        );
+        // The next suite stops at its first failure too
+        let (events, _) = parse(&mut parser, "running 1 test\ntest tests::fails ... FAILED");
+        assert_eq!(events.len(), 2);
+    }
+
+    #[test]
+    fn lines_between_suites_are_not_libtest_output() {
+        let mut parser = HumanParser::default();
+        assert!(parser.feed("   Compiling demo v0.1.0").is_none());
+        assert!(parser.feed("running late").is_none());
+        assert!(parser.abort().is_empty());
+    }
+}
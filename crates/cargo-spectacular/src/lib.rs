@@ -0,0 +1,85 @@
+//! The test runner behind `cargo spectacular`, as a library.
+//!
+//! Use it to build your own runner binary with extra formatters while
+//! keeping everything else `cargo spectacular` does: the flags, the
+//! configuration files, tag selection, sharding, watch mode and the rest.
+//!
+//! ```no_run
+//! use cargo_spectacular::{Formatter, Registry, SuiteResult};
+//! use std::io::{self, Write};
+//! use std::process::ExitCode;
+//!
+//! /// One line per failure, nothing else.
+//! struct Failures;
+//!
+//! impl Formatter for Failures {
+//!     fn suite_started(&mut self, _count: usize, _w: &mut dyn Write) -> io::Result<()> {
+//!         Ok(())
+//!     }
+//!     fn test_started(&mut self, _name: &str, _w: &mut dyn Write) -> io::Result<()> {
+//!         Ok(())
+//!     }
+//!     fn test_passed(&mut self, _: &str, _: Option<f64>, _: &mut dyn Write) -> io::Result<()> {
+//!         Ok(())
+//!     }
+//!     fn test_failed(
+//!         &mut self,
+//!         name: &str,
+//!         _exec_time: Option<f64>,
+//!         _stdout: Option<&str>,
+//!         _message: Option<&str>,
+//!         w: &mut dyn Write,
+//!     ) -> io::Result<()> {
+//!         writeln!(w, "FAILED {name}")
+//!     }
+//!     fn test_ignored(&mut self, _name: &str, _w: &mut dyn Write) -> io::Result<()> {
+//!         Ok(())
+//!     }
+//!     fn suite_finished(&mut self, _: &SuiteResult, _: bool, _: &mut dyn Write) -> io::Result<()> {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! fn main() -> ExitCode {
+//!     let registry = Registry::new().with("failures", |_color| Box::new(Failures));
+//!     cargo_spectacular::main_with(registry)
+//! }
+//! ```
+//!
+//! To run the tests from your own code instead, call [`run`] with a
+//! [`RunConfig`] and any [`Formatter`].
+
+mod artifacts;
+mod backtrace;
+mod bench;
+mod cli;
+mod color;
+mod config;
+mod diagnostics;
+mod event;
+mod formatter;
+mod history;
+mod human;
+mod list;
+mod live;
+mod meta;
+mod profile;
+mod runner;
+mod select;
+mod state;
+mod summary;
+#[cfg(test)]
+mod testing;
+mod tree;
+mod watch;
+
+pub use artifacts::{Target, TargetKind};
+pub use backtrace::Backtrace;
+pub use cli::main_with;
+pub use color::{ColorMode, Palette, Role, Theme};
+pub use event::{BenchResult, DocTestTimes, Event, SuiteEvent, SuiteResult, TestEvent};
+pub use formatter::{FailedTest, Formatter, Registry};
+pub use meta::{Catalog, TestMeta};
+pub use runner::{BUILD_FAILURE, Backend, BackendChoice, RunConfig, RunResult, Status, run};
+pub use select::Shard;
+pub use summary::{BinarySummary, RunSummary, ShardRun};
@@ -0,0 +1,294 @@
+use crate::artifacts::{Target, TestBinary};
+use crate::meta::{Catalog, TestMeta};
+use crate::runner::{self, BUILD_FAILURE, RunConfig};
+use crate::select::{self, TestArgs};
+use crate::tree::{self, Node};
+use serde::Serialize;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+/// The tests of one target, as `list` reports them.
+#[derive(Serialize)]
+struct Listing {
+    label: String,
+    target: Target,
+    tests: Vec<ListedTest>,
+}
+
+#[derive(Serialize)]
+struct ListedTest {
+    /// The libtest name, as filters and `--exact` take it.
+    name: String,
+    /// The module the test is declared in, within its binary.
+    module: Option<String>,
+    /// The `describe` text of the enclosing group.
+    group: Option<String>,
+    /// The `it` text.
+    description: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+    tags: Vec<String>,
+}
+
+impl ListedTest {
+    fn new(name: String, meta: Option<&TestMeta>) -> Self {
+        Self {
+            module: name.rsplit_once("::").map(|(module, _)| module.to_string()),
+            group: meta.and_then(|m| m.group.clone()),
+            description: meta.and_then(|m| m.description.clone()),
+            file: meta.and_then(|m| m.file.clone()),
+            line: meta.and_then(|m| m.line),
+            tags: meta.map(|m| m.tags.clone()).unwrap_or_default(),
+            name,
+        }
+    }
+
+    /// Where the test sits in the tree of its binary.
+    fn node(&self) -> Node<'_> {
+        Node {
+            module: self.module.as_deref().unwrap_or_default(),
+            group: self.group.as_deref(),
+            line: self.line,
+            name: &self.name,
+        }
+    }
+
+    /// The test function name at the end of the libtest name.
+    fn leaf(&self) -> &str {
+        self.name
+            .rsplit_once("::")
+            .map_or(self.name.as_str(), |(_, leaf)| leaf)
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    binaries: Vec<Listing>,
+    total: usize,
+}
+
+/// Build the selected targets and print their tests without running them.
+///
+/// Takes the same selection as a run: packages, `--lib`, `--tag`s, `--shard`
+/// and test name filters. Prints a tree of binary, module and test, or JSON
+/// for tooling.
+pub fn list(config: &RunConfig, json: bool, w: &mut dyn Write) -> io::Result<ExitCode> {
+    let Some(binaries) = runner::build(config)? else {
+        return Ok(ExitCode::from(BUILD_FAILURE));
+    };
+    let args = TestArgs::parse(&config.extra_args);
+
+    let mut catalog = Catalog::default();
+    let mut listings = Vec::new();
+    for binary in &binaries {
+        let label = binary.target.label();
+        catalog.extend(&label, &binary.metadata);
+        let names = select(config, &args, &binary.target, binary.list_tests()?, binary);
+        listings.push(Listing {
+            tests: names
+                .into_iter()
+                .map(|name| {
+                    let meta = catalog.get(Some(&label), &name);
+                    ListedTest::new(name, meta)
+                })
+                .collect(),
+            label,
+            target: binary.target.clone(),
+        });
+    }
+    // Doc-tests are never tagged, as in a run
+    if config.tags.is_empty() && !config.lib_only {
+        for binary in binaries.iter().filter(|b| b.doctest) {
+            let target = binary.doc_target();
+            let names = select(config, &args, &target, target.list_doc_tests()?, binary);
+            listings.push(Listing {
+                label: target.label(),
+                tests: names
+                    .into_iter()
+                    .map(|name| ListedTest {
+                        module: None,
+                        ..ListedTest::new(name, None)
+                    })
+                    .collect(),
+                target,
+            });
+        }
+    }
+    listings.retain(|l| !l.tests.is_empty());
+
+    write_report(listings, json, w)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print `listings` as trees with a count of the tests, or as JSON.
+fn write_report(listings: Vec<Listing>, json: bool, w: &mut dyn Write) -> io::Result<()> {
+    let total = listings.iter().map(|l| l.tests.len()).sum();
+    if json {
+        let report = Report {
+            binaries: listings,
+            total,
+        };
+        serde_json::to_writer_pretty(&mut *w, &report)?;
+        writeln!(w)?;
+    } else {
+        for listing in &listings {
+            write_tree(listing, w)?;
+        }
+        let binaries = listings.len();
+        let tests = if total == 1 { "test" } else { "tests" };
+        let noun = if binaries == 1 { "binary" } else { "binaries" };
+        writeln!(w, "{total} {tests} in {binaries} {noun}")?;
+    }
+    Ok(())
+}
+
+/// The tests of one target that pass the filters, tags and shard of `config`.
+fn select(
+    config: &RunConfig,
+    args: &TestArgs,
+    target: &Target,
+    names: Vec<String>,
+    binary: &TestBinary,
+) -> Vec<String> {
+    let mut names = select::by_tags(
+        names,
+        &binary.metadata,
+        args,
+        &config.tags,
+        &config.exclude_tags,
+    );
+    if let Some(shard) = config.shard {
+        let label = target.label();
+        names.retain(|name| shard.contains(&label, name));
+    }
+    names
+}
+
+/// The target, then its modules and tests as an indented tree, in declaration order.
+fn write_tree(listing: &Listing, w: &mut dyn Write) -> io::Result<()> {
+    let count = listing.tests.len();
+    let noun = if count == 1 { "test" } else { "tests" };
+    writeln!(w, "{} ({count} {noun})", listing.label)?;
+
+    tree::write(&listing.tests, ListedTest::node, write_test, w)?;
+    writeln!(w)
+}
+
+fn write_test(test: &ListedTest, w: &mut dyn Write) -> io::Result<()> {
+    let label = test.description.as_deref().unwrap_or_else(|| test.leaf());
+    write!(w, "{label}")?;
+    if !test.tags.is_empty() {
+        write!(w, " [{}]", test.tags.join(", "))?;
+    }
+    writeln!(w)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn meta(name: &str, description: &str, line: u32, tags: &[&str]) -> TestMeta {
+        TestMeta {
+            path: format!("auth::{name}"),
+            group: Some(String::from("signing in")),
+            description: Some(description.to_string()),
+            file: Some(String::from("tests/auth.rs")),
+            line: Some(line),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            bench: false,
+        }
+    }
+
+    fn binary() -> TestBinary {
+        TestBinary {
+            target: Target::test("auth"),
+            executable: PathBuf::from("/work/target/debug/deps/auth"),
+            doctest: false,
+            metadata: vec![
+                meta("login::accepts", "accepts a password", 12, &["db"]),
+                meta("login::rejects", "rejects a typo", 4, &["slow", "db"]),
+            ],
+        }
+    }
+
+    fn listing() -> Listing {
+        let binary = binary();
+        let mut catalog = Catalog::default();
+        catalog.extend("auth", &binary.metadata);
+        let names = ["login::accepts", "login::rejects", "smoke"];
+        Listing {
+            label: String::from("auth"),
+            target: binary.target,
+            tests: names
+                .into_iter()
+                .map(|name| ListedTest::new(name.to_string(), catalog.get(Some("auth"), name)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn tests_print_as_a_tree_in_declaration_order() {
+        let mut out = Vec::new();
+        write_report(vec![listing()], false, &mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+auth (3 tests)
+  smoke
+  signing in
+    rejects a typo [slow, db]
+    accepts a password [db]
+
+3 tests in 1 binary
+"
+        );
+    }
+
+    #[test]
+    fn json_carries_each_test_with_its_metadata() {
+        let mut out = Vec::new();
+        write_report(vec![listing()], true, &mut out).unwrap();
+        let report: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+        assert_eq!(report["total"], 3);
+        let auth = &report["binaries"][0];
+        assert_eq!(auth["label"], "auth");
+        let rejects = &auth["tests"][1];
+        assert_eq!(rejects["name"], "login::rejects");
+        assert_eq!(rejects["module"], "login");
+        assert_eq!(rejects["description"], "rejects a typo");
+        assert_eq!(rejects["line"], 4);
+        assert_eq!(rejects["tags"], serde_json::json!(["slow", "db"]));
+        assert_eq!(auth["tests"][2]["tags"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn tags_pick_the_tests_listed() {
+        let binary = binary();
+        let names = || {
+            vec![
+                String::from("login::accepts"),
+                String::from("login::rejects"),
+            ]
+        };
+        let args = TestArgs::default();
+
+        let mut config = RunConfig {
+            tags: vec![String::from("slow")],
+            ..RunConfig::default()
+        };
+        assert_eq!(
+            select(&config, &args, &binary.target, names(), &binary),
+            ["login::rejects"]
+        );
+
+        config.tags = vec![String::from("db")];
+        config.exclude_tags = vec![String::from("slow")];
+        assert_eq!(
+            select(&config, &args, &binary.target, names(), &binary),
+            ["login::accepts"]
+        );
+    }
+}
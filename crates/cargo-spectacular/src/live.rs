@@ -0,0 +1,276 @@
+use crate::color::{Palette, Role, reset};
+use crate::event::{BenchResult, Event, SuiteEvent, TestEvent};
+use crossterm::terminal;
+use std::cmp::Reverse;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Seconds a test may run before `--live` highlights it as possibly hung.
+pub const DEFAULT_HANG_AFTER: f64 = 10.0;
+
+/// Tests that finish quicker than this never show up, so fast suites don't flicker.
+const SHOW_AFTER: Duration = Duration::from_secs(1);
+
+/// Rows of running tests shown at most; the rest are counted.
+const MAX_ROWS: usize = 8;
+
+/// Tests that have started and not yet finished, in the order they started.
+#[derive(Debug, Default)]
+pub struct InFlight {
+    tests: Vec<(String, Instant)>,
+}
+
+impl InFlight {
+    /// Follow one event from the test binary.
+    pub fn track(&mut self, event: &Event) {
+        match event {
+            Event::Test(TestEvent::Started { name }) => {
+                self.tests.push((name.clone(), Instant::now()));
+            }
+            Event::Test(
+                TestEvent::Ok { name, .. }
+                | TestEvent::Failed { name, .. }
+                | TestEvent::Ignored { name, .. }
+                | TestEvent::AllowedFail { name },
+            )
+            | Event::Bench(BenchResult { name, .. }) => {
+                self.tests.retain(|(n, _)| n != name);
+            }
+            Event::Test(TestEvent::Timeout { .. }) | Event::Report(_) => {}
+            // A suite that ends takes any unfinished tests with it
+            Event::Suite(
+                SuiteEvent::Started { .. } | SuiteEvent::Ok(_) | SuiteEvent::Failed(_),
+            ) => {
+                self.tests.clear();
+            }
+        }
+    }
+
+    /// The running tests with how long each has run, longest first.
+    pub fn running(&self) -> Vec<(&str, Duration)> {
+        let mut running: Vec<(&str, Duration)> = self
+            .tests
+            .iter()
+            .map(|(name, started)| (name.as_str(), started.elapsed()))
+            .collect();
+        running.sort_by_key(|&(_, time)| Reverse(time));
+        running
+    }
+}
+
+/// The tests still running, drawn under the formatter's output and redrawn
+/// in place as they finish.
+///
+/// Everything written to the terminal while the block is up must go
+/// through [`LiveView::erase`] first and [`LiveView::draw`] after. The view
+/// wraps the formatter's writer to know which column the cursor is left in,
+/// so it can put it back there after drawing below. Without a threshold it
+/// is off and only passes the output through.
+pub struct LiveView<'a> {
+    out: &'a mut dyn Write,
+    inflight: InFlight,
+    hang_after: Option<Duration>,
+    /// Highlight slow and possibly hung tests in color.
+    color: Option<Palette>,
+    label: String,
+    cols: usize,
+    /// The cursor's column in the formatter's output.
+    column: usize,
+    /// Inside an ANSI escape sequence, which takes no columns.
+    escape: bool,
+    /// Lines the block takes up below the cursor.
+    drawn: usize,
+}
+
+impl<'a> LiveView<'a> {
+    pub fn new(out: &'a mut dyn Write, hang_after: Option<f64>, color: Option<Palette>) -> Self {
+        let cols = match terminal::size() {
+            Ok((w, _)) if w > 0 => w as usize,
+            _ => 80,
+        };
+        Self {
+            out,
+            inflight: InFlight::default(),
+            hang_after: hang_after.map(Duration::from_secs_f64),
+            color,
+            label: String::new(),
+            cols,
+            column: 0,
+            escape: false,
+            drawn: 0,
+        }
+    }
+
+    pub fn track(&mut self, label: &str, event: &Event) {
+        label.clone_into(&mut self.label);
+        self.inflight.track(event);
+    }
+
+    /// A line was printed on stderr, leaving the cursor at the start of the next.
+    pub fn printed_line(&mut self) {
+        self.column = 0;
+    }
+
+    /// Remove the block, leaving the cursor where the formatter left it.
+    pub fn erase(&mut self) -> io::Result<()> {
+        if self.drawn > 0 {
+            write!(self.out, "\x1b[J")?;
+            self.drawn = 0;
+        }
+        Ok(())
+    }
+
+    /// Draw the block below the cursor, then move the cursor back.
+    pub fn draw(&mut self) -> io::Result<()> {
+        self.erase()?;
+        let Some(hang_after) = self.hang_after else {
+            return Ok(());
+        };
+        let running = self.inflight.running();
+        let shown: Vec<&(&str, Duration)> = running
+            .iter()
+            .filter(|(_, time)| *time >= SHOW_AFTER)
+            .collect();
+        if shown.is_empty() {
+            return self.out.flush();
+        }
+
+        let noun = if running.len() == 1 { "test" } else { "tests" };
+        write!(
+            self.out,
+            "\n{}",
+            self.fit(&format!(
+                "Running {} {noun} in {}:",
+                running.len(),
+                self.label
+            ))
+        )?;
+        for (name, time) in shown.iter().take(MAX_ROWS) {
+            let line = self.fit(&format!("  {:>7.1}s  {name}", time.as_secs_f64()));
+            writeln!(self.out)?;
+            let role = if *time >= hang_after {
+                Some(Role::Failure)
+            } else if *time >= hang_after / 2 {
+                Some(Role::Warning)
+            } else {
+                None
+            };
+            match (self.color, role) {
+                (Some(palette), Some(role)) => {
+                    palette.paint(self.out, role)?;
+                    write!(self.out, "{line}")?;
+                    reset(self.out)?;
+                }
+                _ => write!(self.out, "{line}")?,
+            }
+        }
+        let rows = shown.len().min(MAX_ROWS);
+        self.drawn = rows + 1;
+        if shown.len() > MAX_ROWS {
+            write!(self.out, "\n  ... and {} more", shown.len() - MAX_ROWS)?;
+            self.drawn += 1;
+        }
+
+        // Relative moves still land right when drawing scrolled the screen
+        write!(self.out, "\x1b[{}A\r", self.drawn)?;
+        if self.column > 0 {
+            write!(self.out, "\x1b[{}C", self.column)?;
+        }
+        self.out.flush()
+    }
+
+    /// Cut a line to the terminal width, so no line of the block wraps.
+    fn fit(&self, line: &str) -> String {
+        line.chars().take(self.cols.saturating_sub(1)).collect()
+    }
+}
+
+impl Write for LiveView<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.out.write(buf)?;
+        // Formatters write escape sequences in pieces, so the state carries over
+        for c in String::from_utf8_lossy(&buf[..written]).chars() {
+            if self.escape {
+                self.escape = !c.is_ascii_alphabetic();
+            } else if c == '\x1b' {
+                self.escape = true;
+            } else if c == '\n' || c == '\r' {
+                self.column = 0;
+            } else {
+                self.column += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(json: &str) -> Event {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn started(name: &str) -> Event {
+        event(&format!(
+            r#"{{ "type": "test", "event": "started", "name": "{name}" }}"#
+        ))
+    }
+
+    #[test]
+    fn in_flight_follows_starts_and_ends() {
+        let mut inflight = InFlight::default();
+        inflight.track(&started("a"));
+        inflight.track(&started("b"));
+        inflight.track(&event(r#"{ "type": "test", "event": "ok", "name": "a" }"#));
+        let names: Vec<&str> = inflight.running().iter().map(|(n, _)| *n).collect();
+        assert_eq!(names, ["b"]);
+
+        inflight.track(&event(
+            r#"{ "type": "suite", "event": "failed", "passed": 1, "failed": 0, "ignored": 0 }"#,
+        ));
+        assert!(inflight.running().is_empty());
+    }
+
+    #[test]
+    fn draws_long_running_tests_below_the_cursor_and_returns() {
+        let mut out = Vec::new();
+        let mut view = LiveView::new(&mut out, Some(10.0), None);
+        view.cols = 40;
+        view.track("auth", &started("quick"));
+        view.inflight.tests.push((
+            String::from("auth::hangs"),
+            Instant::now() - Duration::from_secs(12),
+        ));
+        write!(view, "\x1b[32m..").unwrap();
+        view.draw().unwrap();
+        assert_eq!(view.drawn, 2);
+        view.erase().unwrap();
+        drop(view);
+
+        let text = String::from_utf8(out).unwrap();
+        let (dots, block) = text.split_once("\n").unwrap();
+        assert_eq!(dots, "\x1b[32m..");
+        assert!(block.starts_with("Running 2 tests in auth:\n     12.0s  auth::hangs"));
+        // Back up two lines, then over the two dots; the escape took no columns
+        assert!(block.ends_with("\x1b[2A\r\x1b[2C\x1b[J"), "{block:?}");
+    }
+
+    #[test]
+    fn without_a_threshold_nothing_is_drawn() {
+        let mut out = Vec::new();
+        let mut view = LiveView::new(&mut out, None, None);
+        view.inflight.tests.push((
+            String::from("hangs"),
+            Instant::now() - Duration::from_secs(12),
+        ));
+        view.draw().unwrap();
+        drop(view);
+        assert!(out.is_empty());
+    }
+}
@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Marker the spectacular macros put in front of each test's metadata record.
+const MARKER: &[u8] = b"spectacular:meta:";
+
+/// Metadata embedded in a test binary by `spec!` / `#[test_suite]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct TestMeta {
+    /// Full module path, including the crate name (`my_crate::group::test`).
+    pub path: String,
+    /// The `describe` text, or module name, of the enclosing group.
+    #[serde(default)]
+    pub group: Option<String>,
+    /// The original `it` text; absent for attribute-style tests.
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub file: Option<String>,
+    #[serde(default)]
+    pub line: Option<u32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Declared with `bench` or `#[bench]`.
+    #[serde(default)]
+    pub bench: bool,
+}
+
+impl TestMeta {
+    /// The libtest name: the module path without its leading crate segment.
+    pub fn name(&self) -> &str {
+        self.path
+            .split_once("::")
+            .map_or(self.path.as_str(), |(_, rest)| rest)
+    }
+
+    /// The full sentence, `"<group> <it text>"`, for tests written with `it`.
+    pub fn full_description(&self) -> Option<String> {
+        let desc = self.description.as_deref()?;
+        Some(match self.group.as_deref() {
+            Some(group) => format!("{group} {desc}"),
+            None => desc.to_string(),
+        })
+    }
+
+    /// `file:line` where the test is declared, when the compiler reported it.
+    pub fn location(&self) -> Option<String> {
+        Some(format!("{}:{}", self.file.as_deref()?, self.line?))
+    }
+}
+
+/// Spectacular metadata for every test in a run, keyed by the label of the
+/// test binary and the libtest name.
+///
+/// Test binaries are separate crates, so two of them can hold tests of the
+/// same name: `tests/a.rs` and `tests/b.rs` may both have `auth::passes`.
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    /// Tests by binary label, then by libtest name.
+    tests: HashMap<String, HashMap<String, TestMeta>>,
+    /// The package directory of each test binary, by label.
+    packages: HashMap<String, PathBuf>,
+    /// The workspace root from `cargo metadata`.
+    root: Option<PathBuf>,
+}
+
+impl Catalog {
+    /// An empty catalog for the workspace at `root`.
+    pub(crate) fn new(root: &Path) -> Self {
+        Self {
+            root: Some(root.to_path_buf()),
+            ..Self::default()
+        }
+    }
+
+    /// Record that the binary labelled `binary` belongs to the package whose
+    /// manifest is at `manifest_path`.
+    pub(crate) fn add_package(&mut self, binary: &str, manifest_path: &Path) {
+        if let Some(dir) = manifest_path.parent() {
+            self.packages.insert(binary.to_string(), dir.to_path_buf());
+        }
+    }
+
+    /// Add the metadata scanned from the test binary labelled `binary`.
+    pub(crate) fn extend<'a>(
+        &mut self,
+        binary: &str,
+        metas: impl IntoIterator<Item = &'a TestMeta>,
+    ) {
+        let tests = self.tests.entry(binary.to_string()).or_default();
+        for meta in metas {
+            tests.insert(meta.name().to_string(), meta.clone());
+        }
+    }
+
+    /// Every test, with the label of its binary.
+    pub fn tests(&self) -> impl Iterator<Item = (&str, &TestMeta)> {
+        self.tests
+            .iter()
+            .flat_map(|(binary, tests)| tests.values().map(move |meta| (binary.as_str(), meta)))
+    }
+
+    /// The metadata of test `name` in the binary labelled `binary`.
+    ///
+    /// Without a binary, as before the first `binary_started`, nothing is found.
+    pub fn get(&self, binary: Option<&str>, name: &str) -> Option<&TestMeta> {
+        self.tests.get(binary?)?.get(name)
+    }
+
+    /// The test with the full module path `path`, crate name included.
+    pub fn by_path(&self, path: &str) -> Option<&TestMeta> {
+        self.tests
+            .values()
+            .flat_map(HashMap::values)
+            .find(|meta| meta.path == path)
+    }
+
+    /// The original description for `name`, if it was written with `it`.
+    pub fn description(&self, binary: Option<&str>, name: &str) -> Option<String> {
+        self.get(binary, name).and_then(TestMeta::full_description)
+    }
+
+    /// Where `name` is declared, if known.
+    pub fn location(&self, binary: Option<&str>, name: &str) -> Option<String> {
+        self.get(binary, name).and_then(TestMeta::location)
+    }
+
+    /// The source file behind a path the compiler recorded for a test in
+    /// `binary`, such as the file of a panic location.
+    ///
+    /// Cargo compiles workspace members from the workspace root, so relative
+    /// paths are looked up there first, then in the binary's package.
+    pub fn source_file(&self, binary: Option<&str>, file: &str) -> Option<PathBuf> {
+        let path = Path::new(file);
+        if path.is_absolute() {
+            return path.is_file().then(|| path.to_path_buf());
+        }
+        let package = binary.and_then(|b| self.packages.get(b));
+        self.root
+            .iter()
+            .chain(package)
+            .map(|dir| dir.join(path))
+            .find(|candidate| candidate.is_file())
+    }
+}
+
+/// Scan a compiled test binary for spectacular metadata records.
+///
+/// Binaries without spectacular tests simply yield an empty list.
+pub(crate) fn scan(binary: &Path) -> io::Result<Vec<TestMeta>> {
+    let data = std::fs::read(binary)?;
+    let mut metas: Vec<TestMeta> = Vec::new();
+    let mut seen = HashSet::new();
+    let mut pos = 0;
+
+    while let Some(offset) = find(&data[pos..], MARKER) {
+        let start = pos + offset + MARKER.len();
+        let len = data[start..]
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(data.len() - start);
+        pos = start + len;
+
+        let Ok(meta) = serde_json::from_slice::<TestMeta>(&data[start..start + len]) else {
+            continue;
+        };
+        if seen.insert(meta.path.clone()) {
+            metas.push(meta);
+        }
+    }
+    Ok(metas)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::scratch;
+
+    fn meta(path: &str, description: &str, file: &str, line: u32) -> TestMeta {
+        TestMeta {
+            path: path.to_string(),
+            group: Some(String::from("auth")),
+            description: Some(description.to_string()),
+            file: Some(file.to_string()),
+            line: Some(line),
+            tags: Vec::new(),
+            bench: false,
+        }
+    }
+
+    #[test]
+    fn same_name_in_two_binaries_stays_apart() {
+        let mut catalog = Catalog::default();
+        catalog.extend(
+            "a",
+            &[meta(
+                "a::auth::passes_quickly",
+                "passes quickly",
+                "tests/a.rs",
+                3,
+            )],
+        );
+        catalog.extend(
+            "b",
+            &[meta(
+                "b::auth::passes_quickly",
+                "passes fast",
+                "tests/b.rs",
+                7,
+            )],
+        );
+
+        let name = "auth::passes_quickly";
+        assert_eq!(
+            catalog.description(Some("a"), name).as_deref(),
+            Some("auth passes quickly")
+        );
+        assert_eq!(
+            catalog.description(Some("b"), name).as_deref(),
+            Some("auth passes fast")
+        );
+        assert_eq!(
+            catalog.location(Some("a"), name).as_deref(),
+            Some("tests/a.rs:3")
+        );
+        assert_eq!(
+            catalog.location(Some("b"), name).as_deref(),
+            Some("tests/b.rs:7")
+        );
+        assert_eq!(catalog.tests().count(), 2);
+    }
+
+    #[test]
+    fn unknown_binary_finds_nothing() {
+        let mut catalog = Catalog::default();
+        catalog.extend("a", &[meta("a::auth::passes", "passes", "tests/a.rs", 3)]);
+
+        assert!(catalog.get(None, "auth::passes").is_none());
+        assert!(catalog.get(Some("b"), "auth::passes").is_none());
+        assert_eq!(
+            catalog.by_path("a::auth::passes").map(TestMeta::name),
+            Some("auth::passes")
+        );
+    }
+
+    #[test]
+    fn scan_finds_each_record_once() {
+        let record = br#"spectacular:meta:{"path":"a::auth::passes","tags":["slow"]}"#;
+        let mut data = b"\x7fELF junk".to_vec();
+        for _ in 0..2 {
+            data.extend_from_slice(record);
+            data.push(0);
+        }
+        data.extend_from_slice(b"spectacular:meta:{not json}\0");
+        let path = scratch("meta-scan").join("tests");
+        std::fs::write(&path, &data).unwrap();
+        let metas = scan(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let metas = metas.unwrap();
+        assert_eq!(metas.len(), 1);
+        assert_eq!(metas[0].name(), "auth::passes");
+        assert_eq!(metas[0].tags, ["slow"]);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::artifacts::TargetKind;
+use crate::summary::RunSummary;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// One test, or one group of tests, and the time it took.
+struct Entry {
+    name: String,
+    time: f64,
+    /// How many tests a group holds; `None` for a single test.
+    tests: Option<usize>,
+}
+
+/// List the `count` slowest tests and groups, with their share of the total test time.
+///
+/// Groups are the modules tests are declared in, per binary (`<binary>::<module>`);
+/// tests at a binary's root, and doc-tests, are grouped under the binary alone. Only tests
+/// that libtest timed are counted.
+pub fn write_profile(summary: &RunSummary, count: usize, w: &mut dyn Write) -> io::Result<()> {
+    let mut tests: Vec<Entry> = Vec::new();
+    let mut groups: Vec<Entry> = Vec::new();
+    let mut group_index: HashMap<String, usize> = HashMap::new();
+
+    for binary in &summary.binaries {
+        for (name, time) in &binary.durations {
+            tests.push(Entry {
+                name: format!("{}  {name}", binary.label),
+                time: *time,
+                tests: None,
+            });
+
+            // Doc-test names are `<file> - <item> (line N)`, not module paths
+            let module = match binary.target {
+                Some(ref target) if target.kind == TargetKind::Doc => None,
+                _ => name.rsplit_once("::").map(|(module, _)| module),
+            };
+            let group = match module {
+                Some(module) => format!("{}::{module}", binary.label),
+                None => binary.label.clone(),
+            };
+            let i = *group_index.entry(group.clone()).or_insert_with(|| {
+                groups.push(Entry {
+                    name: group,
+                    time: 0.0,
+                    tests: Some(0),
+                });
+                groups.len() - 1
+            });
+            groups[i].time += time;
+            groups[i].tests = groups[i].tests.map(|n| n + 1);
+        }
+    }
+
+    // Like the formatters' summaries, each section ends with a blank line
+    if tests.is_empty() {
+        writeln!(
+            w,
+            "No test durations were reported, so there is nothing to profile\n"
+        )?;
+        return w.flush();
+    }
+    let total: f64 = tests.iter().map(|t| t.time).sum();
+
+    write_slowest("tests", &mut tests, count, total, w)?;
+    writeln!(w)?;
+    write_slowest("groups", &mut groups, count, total, w)?;
+    writeln!(w)?;
+    w.flush()
+}
+
+fn write_slowest(
+    noun: &str,
+    entries: &mut [Entry],
+    count: usize,
+    total: f64,
+    w: &mut dyn Write,
+) -> io::Result<()> {
+    entries.sort_by(|a, b| b.time.total_cmp(&a.time));
+    let shown = &entries[..count.min(entries.len())];
+    let time: f64 = shown.iter().map(|e| e.time).sum();
+    writeln!(
+        w,
+        "Slowest {} of {} {noun} ({time:.3}s, {:.1}% of {total:.3}s):",
+        shown.len(),
+        entries.len(),
+        share(time, total)
+    )?;
+    for entry in shown {
+        write!(
+            w,
+            "  {:>8.3}s  {:>5.1}%  {}",
+            entry.time,
+            share(entry.time, total),
+            entry.name
+        )?;
+        if let Some(n) = entry.tests {
+            let plural = if n == 1 { "test" } else { "tests" };
+            write!(w, "  ({n} {plural})")?;
+        }
+        writeln!(w)?;
+    }
+    Ok(())
+}
+
+fn share(time: f64, total: f64) -> f64 {
+    if total > 0.0 {
+        time / total * 100.0
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifacts::Target;
+    use crate::event::SuiteResult;
+
+    fn summary() -> RunSummary {
+        let mut summary = RunSummary::default();
+        summary.test_timed("login::accepts", 0.3);
+        summary.test_timed("login::rejects", 0.1);
+        summary.test_timed("smoke", 0.2);
+        summary.push("auth", None, &SuiteResult::default());
+        summary.test_timed("src/lib.rs - parse (line 3)", 0.4);
+        let doc = Target {
+            kind: TargetKind::Doc,
+            ..Target::test("demo")
+        };
+        summary.push("doc-tests", Some(&doc), &SuiteResult::default());
+        summary
+    }
+
+    #[test]
+    fn lists_the_slowest_tests_and_groups() {
+        let mut out = Vec::new();
+        write_profile(&summary(), 2, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+Slowest 2 of 4 tests (0.700s, 70.0% of 1.000s):
+     0.400s   40.0%  doc-tests  src/lib.rs - parse (line 3)
+     0.300s   30.0%  auth  login::accepts
+
+Slowest 2 of 3 groups (0.800s, 80.0% of 1.000s):
+     0.400s   40.0%  auth::login  (2 tests)
+     0.400s   40.0%  doc-tests  (1 test)
+
+"
+        );
+    }
+
+    #[test]
+    fn says_so_without_durations() {
+        let mut out = Vec::new();
+        write_profile(&RunSummary::default(), 10, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "No test durations were reported, so there is nothing to profile\n\n"
+        );
+    }
+}
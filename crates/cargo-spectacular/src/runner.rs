@@ -1,21 +1,235 @@
-use crate::event::{Event, SuiteEvent, TestEvent};
+use crate::artifacts::{self, Target, TestBinary};
+use crate::backtrace::{self, Backtrace};
+use crate::bench::{self, Measurement};
+use crate::color::{ColorMode, Palette, Theme};
+use crate::diagnostics;
+use crate::event::{Event, SuiteEvent, SuiteResult, TestEvent};
 use crate::formatter::Formatter;
-use std::io::{self, BufRead, BufReader, Write};
-use std::process::{Command, ExitCode, Stdio};
+use crate::history;
+use crate::human::HumanParser;
+use crate::live::{self, LiveView};
+use crate::meta::Catalog;
+use crate::profile;
+use crate::select::{self, Shard, TestArgs};
+use crate::state;
+use crate::summary::{Rerun, RunSummary, ShardRun};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Child, Command, ExitCode};
+#[cfg(unix)]
+use std::sync::Once;
+#[cfg(unix)]
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
 
+/// Exit code when the tests could not be built, as opposed to tests failing.
+pub const BUILD_FAILURE: u8 = 2;
+
+/// How often the live view is redrawn while no output arrives.
+const TICK: Duration = Duration::from_millis(250);
+
+/// libtest flags that switch the test binaries to JSON events, with
+/// per-test durations for `--profile` and the duration history.
+const JSON_ARGS: [&str; 5] = [
+    "--format",
+    "json",
+    "-Z",
+    "unstable-options",
+    "--report-time",
+];
+
+/// How test results are read from libtest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// libtest's JSON events (`--format json -Z unstable-options --report-time`), nightly only.
+    Json,
+    /// libtest's default human-readable output, parsed line by line.
+    Human,
+}
+
+/// The backend asked for with `--backend`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BackendChoice {
+    /// JSON on nightly toolchains, human-readable output on stable.
+    #[default]
+    Auto,
+    /// Always [`Backend::Json`]; an error on stable toolchains.
+    Json,
+    /// Always [`Backend::Human`].
+    Human,
+}
+
+impl BackendChoice {
+    pub fn parse(name: &str) -> Option<BackendChoice> {
+        match name {
+            "auto" => Some(BackendChoice::Auto),
+            "json" => Some(BackendChoice::Json),
+            "human" => Some(BackendChoice::Human),
+            _ => None,
+        }
+    }
+}
+
+impl Backend {
+    /// Pick the JSON backend when the toolchain lets libtest emit JSON.
+    fn detect() -> Backend {
+        if nightly() {
+            Backend::Json
+        } else {
+            Backend::Human
+        }
+    }
+
+    /// Arguments for the test binaries that select this backend's output.
+    fn test_args(self) -> &'static [&'static str] {
+        match self {
+            Backend::Json => &JSON_ARGS,
+            Backend::Human => &[],
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Backend::Json => "libtest JSON events",
+            Backend::Human => "libtest human-readable output",
+        }
+    }
+}
+
+/// Whether the toolchain cargo will use accepts `-Z unstable-options`.
+///
+/// Asks `$RUSTC` when it is set, since cargo builds with it, and otherwise
+/// the same `cargo` the tests run with, so that the toolchain rustup picks
+/// for it (`rust-toolchain.toml`, `RUSTUP_TOOLCHAIN`, `cargo +nightly`) is
+/// the one asked. Honours `RUSTC_BOOTSTRAP`, which libtest checks too.
+fn nightly() -> bool {
+    if std::env::var_os("RUSTC_BOOTSTRAP").is_some_and(|v| v == "1") {
+        return true;
+    }
+    let program = std::env::var_os("RUSTC").unwrap_or_else(|| "cargo".into());
+    let Ok(output) = Command::new(program).arg("-vV").output() else {
+        return false;
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix("release: "))
+        .any(|release| release.contains("-nightly") || release.contains("-dev"))
+}
+
+/// What to build and run, mirroring the command-line flags.
+///
+/// Start from [`RunConfig::default`] and set the fields you need; fields may
+/// be added in minor releases.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct RunConfig {
+    /// `--manifest-path`
     pub manifest_path: Option<String>,
+    /// `--package`
     pub package: Option<String>,
+    /// Test only the library (`--lib`).
     pub lib_only: bool,
+    /// Test every package in the workspace (`--all`).
     pub all: bool,
+    /// Run only tests with any of these tags.
+    pub tags: Vec<String>,
+    /// Skip tests with any of these tags.
+    pub exclude_tags: Vec<String>,
+    /// `--backend`; `Auto` picks a backend from the toolchain.
+    pub backend: BackendChoice,
+    /// Print build warnings in full instead of counting them.
+    pub show_warnings: bool,
+    /// Print compile errors as rustc renders them instead of compactly.
+    pub full_errors: bool,
+    /// After the run, list this many of the slowest tests and groups.
+    pub profile: Option<usize>,
+    /// How many times its median duration a test may take before it's slower than usual.
+    pub slowdown_factor: f64,
+    /// Fail the run when a test is slower than usual.
+    pub fail_on_slowdown: bool,
+    /// Run only this shard of the tests.
+    pub shard: Option<Shard>,
+    /// Run exactly these tests instead of the usual selection.
+    pub(crate) only: Option<Vec<Rerun>>,
+    /// Show the tests that are still running under the output (`--live`).
+    pub live: bool,
+    /// Seconds after which `live` highlights a running test as possibly hung.
+    pub hang_after: f64,
+    /// Also print cargo's status lines and any libtest output that isn't understood.
+    pub verbose: bool,
+    /// Run only the benchmarks, in release mode, and measure them (`--bench`).
+    pub bench: bool,
+    /// Compare the measurements with the baseline saved under this name.
+    pub baseline: Option<String>,
+    /// Save the measurements as a baseline under this name.
+    pub save_baseline: Option<String>,
+    /// Have failing tests print a backtrace, cut down to the user's frames.
+    pub backtrace: Option<Backtrace>,
+    /// Stop the run at the first failing test (`--fail-fast`).
+    pub fail_fast: bool,
+    /// When to color what the runner prints on stderr and asks cargo to color.
+    pub color: ColorMode,
+    /// The colors to use when `color` allows them (`--theme`).
+    pub palette: Palette,
+    /// Arguments for the test binaries, as after `--`.
     pub extra_args: Vec<String>,
 }
 
-pub fn run(
-    config: &RunConfig,
-    formatter: &mut dyn Formatter,
-    w: &mut dyn Write,
-) -> io::Result<ExitCode> {
+impl Default for RunConfig {
+    /// The whole current package, as `cargo spectacular` with no flags.
+    fn default() -> Self {
+        Self {
+            manifest_path: None,
+            package: None,
+            lib_only: false,
+            all: false,
+            tags: Vec::new(),
+            exclude_tags: Vec::new(),
+            backend: BackendChoice::Auto,
+            show_warnings: false,
+            full_errors: false,
+            profile: None,
+            slowdown_factor: history::DEFAULT_FACTOR,
+            fail_on_slowdown: false,
+            shard: None,
+            only: None,
+            live: false,
+            hang_after: live::DEFAULT_HANG_AFTER,
+            verbose: false,
+            bench: false,
+            baseline: None,
+            save_baseline: None,
+            backtrace: None,
+            fail_fast: false,
+            color: ColorMode::Auto,
+            palette: Palette::new(Theme::Default),
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+impl RunConfig {
+    /// The palette for output to stdout, or `None` when it isn't colored.
+    pub fn palette(&self) -> Option<Palette> {
+        self.color.stdout().then_some(self.palette)
+    }
+
+    /// Whether the runner has to pick the tests itself, by tag, shard or benchmark.
+    fn selects_tests(&self) -> bool {
+        !self.tags.is_empty() || !self.exclude_tags.is_empty() || self.shard.is_some() || self.bench
+    }
+
+    /// The hang threshold when the live view is on; it needs a terminal to draw in.
+    fn live(&self) -> Option<f64> {
+        (self.live && io::stdout().is_terminal()).then_some(self.hang_after)
+    }
+}
+
+/// A `cargo test` command with the package selection from `config`.
+pub fn cargo_test(config: &RunConfig) -> Command {
     let mut cmd = Command::new("cargo");
     cmd.arg("test");
 
@@ -31,81 +245,1049 @@ pub fn run(
     if config.all {
         cmd.arg("--all");
     }
+    if config.bench {
+        cmd.arg("--release");
+    }
+    cmd
+}
+
+/// How a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Passed,
+    /// A test failed, or one was slower than usual under `fail_on_slowdown`.
+    Failed,
+    BuildFailed,
+}
+
+/// The outcome of [`run`]: its status and what each binary reported.
+#[non_exhaustive]
+pub struct RunResult {
+    pub status: Status,
+    pub summary: RunSummary,
+}
+
+impl RunResult {
+    /// The exit code `cargo spectacular` ends with: 0, 1, or [`BUILD_FAILURE`].
+    pub fn exit_code(&self) -> ExitCode {
+        match self.status {
+            Status::Passed => ExitCode::SUCCESS,
+            Status::Failed => ExitCode::FAILURE,
+            Status::BuildFailed => ExitCode::from(BUILD_FAILURE),
+        }
+    }
+}
+
+/// Build the tests, run them, and send every event to `formatter`, which
+/// writes to `w`.
+///
+/// Reads libtest's output with the configured [`Backend`], records the
+/// failures and durations under the target directory as the command-line
+/// runner does, and reports build progress and warnings on stderr. An `Err`
+/// means the runner itself failed, not the tests.
+pub fn run(
+    config: &RunConfig,
+    formatter: &mut dyn Formatter,
+    w: &mut dyn Write,
+) -> io::Result<RunResult> {
+    let backend = match config.backend {
+        BackendChoice::Json if !nightly() => {
+            return Err(io::Error::other(
+                "the json backend needs a nightly toolchain (libtest's --format json is unstable); \
+                 use --backend human or --backend auto",
+            ));
+        }
+        BackendChoice::Json => Backend::Json,
+        BackendChoice::Human => Backend::Human,
+        BackendChoice::Auto => Backend::detect(),
+    };
+    eprintln!("cargo-spectacular: reading {}", backend.describe());
+    if config.profile.is_some() && backend == Backend::Human {
+        eprintln!(
+            "cargo-spectacular: --profile needs per-test durations, which libtest only reports on nightly"
+        );
+    }
+    if config.fail_on_slowdown && backend == Backend::Human {
+        eprintln!(
+            "cargo-spectacular: --fail-on-slowdown needs per-test durations, which libtest only reports on nightly"
+        );
+    }
+
+    let Some(binaries) = build(config)? else {
+        formatter.finish(w)?;
+        return Ok(RunResult {
+            status: Status::BuildFailed,
+            summary: RunSummary::default(),
+        });
+    };
+
+    let workspace = artifacts::workspace(config)?;
+    let mut catalog = Catalog::new(&workspace.workspace_root);
+    for binary in &binaries {
+        let label = binary.target.label();
+        let manifest_path = Path::new(&binary.target.manifest_path);
+        catalog.extend(&label, &binary.metadata);
+        catalog.add_package(&label, manifest_path);
+        if binary.doctest {
+            catalog.add_package(&binary.doc_target().label(), manifest_path);
+        }
+    }
+    formatter.metadata(&catalog);
+
+    // A missing baseline fails the run before anything is measured
+    let bench_results = if config.bench {
+        let dir = state::dir(config)?;
+        let baseline = match config.baseline {
+            Some(ref name) => Some(bench::load_baseline(&dir, name)?),
+            None => None,
+        };
+        Some((bench::start(&dir)?, baseline))
+    } else {
+        None
+    };
+
+    let tests = Tests {
+        binaries: &binaries,
+        root: &workspace.workspace_root,
+    };
+    let mut summary = RunSummary::default();
+    let success = if let Some(ref only) = config.only {
+        let count = only.iter().map(|rerun| rerun.tests.len()).sum();
+        formatter.run_started(Some(count), w)?;
+        run_only(config, backend, only, &tests, &mut summary, formatter, w)?
+    } else if config.selects_tests() {
+        let (selected, shard_run) = select_tests(config, &binaries)?;
+        summary.shard = shard_run;
+        formatter.run_started(Some(selected.iter().filter_map(|s| s.count).sum()), w)?;
+        let selection = Selection {
+            targets: selected,
+            bench_results: bench_results.as_ref().map(|(path, _)| path.as_path()),
+        };
+        run_selected(
+            config,
+            backend,
+            selection,
+            &tests,
+            &mut summary,
+            formatter,
+            w,
+        )?
+    } else {
+        let args = TestArgs::parse(&config.extra_args);
+        let count = if formatter.wants_test_count() {
+            let counts = binaries.iter().map(|binary| selected_count(binary, &args));
+            Some(counts.sum::<io::Result<usize>>()?)
+        } else {
+            None
+        };
+        formatter.run_started(count, w)?;
+        let mut cmd = cargo_test(config);
+        // Keep going past failing binaries so the summary covers all of them
+        if !config.fail_fast {
+            cmd.arg("--no-fail-fast");
+        }
+        cmd.arg("--message-format=json");
+        // Separator + backend format flags + any extra user args
+        cmd.arg("--");
+        cmd.args(backend.test_args());
+        cmd.args(&config.extra_args);
+        let success = stream(cmd, backend, config, &tests, &mut summary, formatter, w)?;
+        if summary.stopped() {
+            for binary in &binaries {
+                let ran = summary
+                    .binaries
+                    .iter()
+                    .any(|b| b.target.as_ref() == Some(&binary.target));
+                if !ran {
+                    summary.stop(selected_count(binary, &args)?);
+                }
+            }
+        }
+        success
+    };
+    formatter.run_finished(&summary, w)?;
+    if let Some(count) = config.profile {
+        let mut report = Vec::new();
+        profile::write_profile(&summary, count, &mut report)?;
+        formatter.report(&String::from_utf8_lossy(&report), w)?;
+    }
+    if let Some((ref results, ref baseline)) = bench_results {
+        report_benchmarks(config, results, baseline.as_deref(), &catalog, formatter, w)?;
+    }
+
+    // Losing the records shouldn't fail a run that otherwise finished
+    let mut slowdowns = Vec::new();
+    match state::dir(config) {
+        Ok(dir) => {
+            if let Err(e) = state::save_failures(&dir, &summary.failures()) {
+                eprintln!("cargo-spectacular: could not record failures: {e}");
+            }
+            // Release builds run at another speed: their durations are kept apart
+            let profile = if config.bench {
+                history::Profile::Release
+            } else {
+                history::Profile::Test
+            };
+            match history::record(&dir, profile, &summary, config.slowdown_factor) {
+                Ok(found) => slowdowns = found,
+                Err(e) => eprintln!("cargo-spectacular: could not record durations: {e}"),
+            }
+        }
+        Err(e) => eprintln!("cargo-spectacular: could not record this run: {e}"),
+    }
+    let mut report = Vec::new();
+    history::write_slowdowns(&slowdowns, config.slowdown_factor, &mut report)?;
+    if !report.is_empty() {
+        formatter.report(&String::from_utf8_lossy(&report), w)?;
+    }
+
+    formatter.finish(w)?;
+
+    let slowdown_fails = config.fail_on_slowdown && !slowdowns.is_empty();
+    let status = if success && !slowdown_fails {
+        Status::Passed
+    } else {
+        Status::Failed
+    };
+    Ok(RunResult { status, summary })
+}
+
+/// Build the test targets and report the build on stderr.
+///
+/// Returns `None` when the build failed, after listing the errors.
+pub fn build(config: &RunConfig) -> io::Result<Option<Vec<TestBinary>>> {
+    let build = artifacts::build(config)?;
+    let color = config.color.stderr();
+    let mut stderr = io::stderr().lock();
+
+    if config.show_warnings {
+        for warning in &build.warnings {
+            warning.write_rendered(color, &mut stderr)?;
+        }
+    } else if !build.warnings.is_empty() {
+        let count = build.warnings.len();
+        let noun = if count == 1 { "warning" } else { "warnings" };
+        writeln!(
+            stderr,
+            "{count} build {noun} (run with --show-warnings to see them)"
+        )?;
+    }
+
+    if build.success {
+        for line in &build.output {
+            writeln!(stderr, "{line}")?;
+        }
+        return Ok(Some(build.binaries));
+    }
+
+    for error in &build.errors {
+        if config.full_errors {
+            error.write_rendered(color, &mut stderr)?;
+        } else {
+            error.write_compact(color, &mut stderr)?;
+            writeln!(stderr)?;
+        }
+    }
+    for line in &build.output {
+        writeln!(stderr, "{line}")?;
+    }
+    let count = build.errors.len();
+    let noun = if count == 1 { "error" } else { "errors" };
+    writeln!(stderr, "Build failed with {count} {noun}")?;
+    Ok(None)
+}
 
-    // Separator + JSON format flags + any extra user args
-    cmd.arg("--");
-    cmd.args(["--format", "json", "-Z", "unstable-options"]);
-    cmd.args(&config.extra_args);
+/// The built test binaries a run goes through, and their workspace.
+struct Tests<'a> {
+    binaries: &'a [TestBinary],
+    /// The workspace root from `cargo metadata`.
+    root: &'a Path,
+}
 
-    cmd.stdout(Stdio::piped());
-    cmd.stderr(Stdio::inherit());
+/// One target of a selected run and the tests to run in it.
+struct Selected {
+    target: Target,
+    /// The tests to run by name; `None` runs the target with the user's arguments.
+    names: Option<Vec<String>>,
+    /// How many tests that is, when it could be counted ahead of time.
+    count: Option<usize>,
+}
+
+/// Pick the tests whose tags pass `--tag` / `--exclude-tag` and that belong to
+/// the `--shard`, before any of them run.
+///
+/// Binaries are narrowed to exact names from their spectacular metadata, and
+/// those without a selected test are left out. Doc-tests are listed through
+/// `cargo test --doc` and sharded one by one like any other test; without
+/// `--shard` they run as they are, uncounted.
+fn select_tests(
+    config: &RunConfig,
+    binaries: &[TestBinary],
+) -> io::Result<(Vec<Selected>, Option<ShardRun>)> {
+    let args = TestArgs::parse(&config.extra_args);
+    let mut selected = Vec::new();
+    let mut shard_run = config.shard.map(|shard| ShardRun {
+        shard,
+        selected: 0,
+        total: 0,
+    });
+
+    for binary in binaries {
+        if binary.metadata.is_empty() && config.shard.is_none() {
+            // No spectacular tests, so nothing here carries a tag or benchmarks
+            if config.tags.is_empty() && !config.bench {
+                selected.push(Selected {
+                    target: binary.target.clone(),
+                    names: None,
+                    count: Some(selected_count(binary, &args)?),
+                });
+            }
+            continue;
+        }
+        let mut names = select::by_tags(
+            binary.list_tests()?,
+            &binary.metadata,
+            &args,
+            &config.tags,
+            &config.exclude_tags,
+        );
+        if config.bench {
+            names.retain(|name| binary.metadata.iter().any(|m| m.bench && m.name() == name));
+        }
+        if let Some(ref mut shard_run) = shard_run {
+            let label = binary.target.label();
+            shard_run.total += names.len();
+            names.retain(|name| shard_run.shard.contains(&label, name));
+            shard_run.selected += names.len();
+        }
+        if !names.is_empty() {
+            selected.push(Selected {
+                target: binary.target.clone(),
+                count: Some(names.len()),
+                names: Some(names),
+            });
+        }
+    }
+
+    // Doc-tests are never tagged or benchmarks: they only run when no `--tag`
+    // or `--bench` is requested
+    if config.tags.is_empty() && !config.lib_only && !config.bench {
+        for binary in binaries.iter().filter(|b| b.doctest) {
+            let target = binary.doc_target();
+            let Some(ref mut shard_run) = shard_run else {
+                selected.push(Selected {
+                    target,
+                    names: None,
+                    count: None,
+                });
+                continue;
+            };
+            let label = target.label();
+            let mut names = target.list_doc_tests()?;
+            names.retain(|name| args.matches(name));
+            shard_run.total += names.len();
+            names.retain(|name| shard_run.shard.contains(&label, name));
+            shard_run.selected += names.len();
+            if !names.is_empty() {
+                selected.push(Selected {
+                    target,
+                    count: Some(names.len()),
+                    names: Some(names),
+                });
+            }
+        }
+    }
+
+    Ok((selected, shard_run))
+}
+
+/// The tests [`select_tests`] picked, ready to run.
+struct Selection<'a> {
+    targets: Vec<Selected>,
+    /// Where a `--bench` run's measurements go.
+    bench_results: Option<&'a Path>,
+}
+
+/// Run a selection, target by target.
+fn run_selected(
+    config: &RunConfig,
+    backend: Backend,
+    selection: Selection,
+    tests: &Tests,
+    summary: &mut RunSummary,
+    formatter: &mut dyn Formatter,
+    w: &mut dyn Write,
+) -> io::Result<bool> {
+    let args = TestArgs::parse(&config.extra_args);
+    let mut success = true;
+
+    for Selected {
+        target,
+        names,
+        count,
+    } in selection.targets
+    {
+        let command = || {
+            let mut cmd = target.cargo_test();
+            if let Some(results) = selection.bench_results {
+                // One at a time, so benchmarks don't slow each other down
+                cmd.arg("--release")
+                    .env(bench::RESULTS_ENV, results)
+                    .env("RUST_TEST_THREADS", "1");
+            }
+            cmd.arg("--message-format=json");
+            cmd.arg("--");
+            cmd.args(backend.test_args());
+            cmd
+        };
+        let Some(names) = names else {
+            if summary.stopped() {
+                // Uncounted doc-tests can't be added to what didn't run
+                summary.stop(count.unwrap_or(0));
+                continue;
+            }
+            let mut cmd = command();
+            cmd.args(&config.extra_args);
+            success &= stream(cmd, backend, config, tests, summary, formatter, w)?;
+            continue;
+        };
+        let run = Exact {
+            command: || {
+                let mut cmd = command();
+                cmd.args(&args.rest);
+                cmd
+            },
+            names,
+            exact: target.takes_exact(),
+        };
+        success &= run.stream(backend, config, tests, summary, formatter, w)?;
+    }
+
+    Ok(success)
+}
+
+/// Show the measurements of a `--bench` run, then save them as a baseline if asked.
+fn report_benchmarks(
+    config: &RunConfig,
+    results: &Path,
+    baseline: Option<&[Measurement]>,
+    catalog: &Catalog,
+    formatter: &mut dyn Formatter,
+    w: &mut dyn Write,
+) -> io::Result<()> {
+    let measurements = bench::read(results)?;
+    let compared = config.baseline.as_deref().zip(baseline);
+    let mut report = Vec::new();
+    bench::write_table(&measurements, compared, catalog, &mut report)?;
+    formatter.report(&String::from_utf8_lossy(&report), w)?;
+
+    if let Some(ref name) = config.save_baseline
+        && !measurements.is_empty()
+    {
+        bench::save_baseline(&state::dir(config)?, name, &measurements)?;
+        eprintln!("cargo-spectacular: saved the measurements as baseline {name}");
+    }
+    Ok(())
+}
+
+/// Run exactly the given tests, target by target.
+///
+/// The user's own filters are dropped; their other test arguments still apply.
+fn run_only(
+    config: &RunConfig,
+    backend: Backend,
+    only: &[Rerun],
+    tests: &Tests,
+    summary: &mut RunSummary,
+    formatter: &mut dyn Formatter,
+    w: &mut dyn Write,
+) -> io::Result<bool> {
+    let args = TestArgs::parse(&config.extra_args);
+    let mut success = true;
+
+    for rerun in only {
+        let run = Exact {
+            command: || {
+                let mut cmd = rerun.target.cargo_test();
+                cmd.arg("--message-format=json");
+                cmd.arg("--");
+                cmd.args(backend.test_args());
+                cmd.args(&args.rest);
+                cmd
+            },
+            names: rerun.tests.clone(),
+            exact: rerun.target.takes_exact(),
+        };
+        success &= run.stream(backend, config, tests, summary, formatter, w)?;
+    }
+
+    Ok(success)
+}
+
+/// Tests picked out by name.
+struct Exact<F> {
+    /// Builds the `cargo test` command up to the test names
+    command: F,
+    names: Vec<String>,
+    /// Pass `--exact`; see [`Target::takes_exact`]
+    exact: bool,
+}
+
+impl<F: Fn() -> Command> Exact<F> {
+    /// Run the tests in as many `cargo test`s as it takes to keep each command
+    /// line short enough to start, counting the rest as not run once stopped.
+    fn stream(
+        self,
+        backend: Backend,
+        config: &RunConfig,
+        tests: &Tests,
+        summary: &mut RunSummary,
+        formatter: &mut dyn Formatter,
+        w: &mut dyn Write,
+    ) -> io::Result<bool> {
+        let mut success = true;
+        for batch in select::batches(self.names, select::ARGS_LIMIT) {
+            if summary.stopped() {
+                summary.stop(batch.len());
+                continue;
+            }
+            let mut cmd = (self.command)();
+            if self.exact {
+                cmd.arg("--exact");
+            }
+            cmd.args(batch);
+            success &= stream(cmd, backend, config, tests, summary, formatter, w)?;
+        }
+        Ok(success)
+    }
+}
+
+/// How many of `binary`'s tests the name filters in `args` select.
+fn selected_count(binary: &TestBinary, args: &TestArgs) -> io::Result<usize> {
+    Ok(binary
+        .list_tests()?
+        .iter()
+        .filter(|name| args.matches(name))
+        .count())
+}
+
+/// Print every tag found in the selected test binaries with its test count.
+pub fn list_tags(config: &RunConfig, w: &mut dyn Write) -> io::Result<ExitCode> {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let Some(binaries) = build(config)? else {
+        return Ok(ExitCode::from(BUILD_FAILURE));
+    };
+    for binary in binaries {
+        for meta in binary.metadata {
+            for tag in meta.tags {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+    }
+
+    if counts.is_empty() {
+        writeln!(w, "No tagged tests found")?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let width = counts.keys().map(String::len).max().unwrap_or(0);
+    for (tag, count) in &counts {
+        let noun = if *count == 1 { "test" } else { "tests" };
+        writeln!(w, "{tag:<width$}  {count} {noun}")?;
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Spawn a `cargo test` command and feed its test events to the formatter.
+///
+/// Cargo's JSON messages are skipped: the build step has already reported them.
+/// Cargo's stderr shares the pipe with the test binaries' stdout, so its
+/// `Running ...` lines arrive in order with the events and mark where each
+/// binary starts. Anything that is neither a test event nor a cargo status line
+/// is passed on to stderr.
+///
+/// With a `live` hang threshold, the tests still running are shown under
+/// the output and redrawn every [`TICK`], so a stalled test stays in view.
+///
+/// Returns `true` when every test passed and cargo exited successfully.
+fn stream(
+    mut cmd: Command,
+    backend: Backend,
+    config: &RunConfig,
+    tests: &Tests,
+    summary: &mut RunSummary,
+    formatter: &mut dyn Formatter,
+    w: &mut dyn Write,
+) -> io::Result<bool> {
+    let (reader, writer) = io::pipe()?;
+    cmd.stdout(writer.try_clone()?);
+    cmd.stderr(writer);
+    if config.color.stderr() {
+        cmd.env("CARGO_TERM_COLOR", "always");
+    }
+    if let Some(backtrace) = config.backtrace {
+        cmd.env("RUST_BACKTRACE", backtrace.env_value());
+    }
+    if config.fail_fast {
+        own_process_group(&mut cmd);
+    }
 
     let mut child = cmd
         .spawn()
         .map_err(|e| io::Error::new(e.kind(), format!("Failed to spawn cargo test: {e}")))?;
+    // The command holds the pipe's write ends; drop them so the reader sees EOF
+    drop(cmd);
+    let _forward = config.fail_fast.then(|| Forward::new(&child));
 
-    let stdout = child.stdout.take().expect("stdout was piped");
-    let reader = BufReader::new(stdout);
+    // Read on another thread, so the live view can tick while a test hangs
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(reader).lines() {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
 
-    let mut any_failure = false;
+    // Backtraces are only filtered when --backtrace asked for them
+    let backtraces = config.backtrace.map(|_| tests.root);
+    let mut view = LiveView::new(w, config.live(), config.palette());
+    let mut human = if config.fail_fast {
+        HumanParser::fail_fast()
+    } else {
+        HumanParser::default()
+    };
+    let mut label = String::from("tests");
+    let mut target = None;
 
-    for line in reader.lines() {
-        let line = line?;
+    let mut any_failure = false;
+    // The binary that is running, for a partial result if the run stops in it
+    let mut running: Option<Tally> = None;
 
-        let event: Event = match serde_json::from_str(&line) {
-            Ok(e) => e,
-            Err(_) => continue, // skip non-JSON lines (e.g. compile output leaking through)
+    loop {
+        let line = match lines.recv_timeout(TICK) {
+            Ok(line) => line?,
+            Err(RecvTimeoutError::Timeout) => {
+                view.draw()?;
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        view.erase()?;
+        let events = match backend {
+            Backend::Json => serde_json::from_str::<Event>(&line).ok().map(|e| vec![e]),
+            Backend::Human => human.feed(&line),
         };
+        let mut test_failed = false;
+        match events {
+            Some(events) => {
+                for event in events {
+                    Tally::track(&mut running, &event);
+                    test_failed |= matches!(event, Event::Test(TestEvent::Failed { .. }));
+                    view.track(&label, &event);
+                    if let Event::Test(TestEvent::Timeout { ref name }) = event {
+                        eprintln!(
+                            "cargo-spectacular: {label} {name} has been running for over 60 seconds"
+                        );
+                        view.printed_line();
+                    }
+                    any_failure |= dispatch(
+                        event,
+                        &label,
+                        target.as_ref(),
+                        backtraces,
+                        summary,
+                        formatter,
+                        &mut view,
+                    )?;
+                }
+            }
+            // Diagnostics were already reported by the build step
+            None if artifacts::is_cargo_message(&line) => {}
+            // JSON libtest sent that isn't in the event schema, e.g. from a newer toolchain
+            None if is_json_object(&line) => {
+                if config.verbose {
+                    eprintln!("cargo-spectacular: unrecognized libtest output: {line}");
+                    view.printed_line();
+                }
+            }
+            None => {
+                if let Some(next) = artifacts::running(&line, tests.binaries) {
+                    // The binary before it may have stopped without ending its
+                    // suite, which stops a --fail-fast run like a failing test
+                    for event in unfinished(backend, &mut human, &mut running) {
+                        Tally::track(&mut running, &event);
+                        test_failed = true;
+                        any_failure |= dispatch(
+                            event,
+                            &label,
+                            target.as_ref(),
+                            backtraces,
+                            summary,
+                            formatter,
+                            &mut view,
+                        )?;
+                    }
+                    if !(config.fail_fast && test_failed) {
+                        formatter.binary_started(&next.label, &mut view)?;
+                        label = next.label;
+                        target = next.target;
+                    }
+                }
+                // Cargo's status lines, its rerun hints and libtest's spacing are
+                // noise next to the formatter's own sections, unless asked for
+                let noise = line.trim().is_empty()
+                    || diagnostics::is_status_line(&line)
+                    || diagnostics::is_rerun_hint(&line);
+                if !noise || (config.verbose && !line.trim().is_empty()) {
+                    eprintln!("{line}");
+                    view.printed_line();
+                }
+            }
+        }
+        if config.fail_fast && test_failed {
+            kill_tree(&mut child)?;
+            child.wait()?;
+            let mut not_run = 0;
+            if let Some(tally) = running.take() {
+                not_run = tally.not_run();
+                let event = Event::Suite(SuiteEvent::Failed(tally.result));
+                dispatch(
+                    event,
+                    &label,
+                    target.as_ref(),
+                    backtraces,
+                    summary,
+                    formatter,
+                    &mut view,
+                )?;
+            }
+            summary.stop(not_run);
+            return Ok(false);
+        }
+        view.draw()?;
+    }
+    view.erase()?;
+    for event in unfinished(backend, &mut human, &mut running) {
+        any_failure |= dispatch(
+            event,
+            &label,
+            target.as_ref(),
+            backtraces,
+            summary,
+            formatter,
+            w,
+        )?;
+    }
+
+    let status = child.wait()?;
+
+    Ok(!any_failure && status.success())
+}
+
+/// The events that end a binary which stopped without ending its suite, as
+/// when a test aborts the process: a failed suite with what it reported.
+fn unfinished(
+    backend: Backend,
+    human: &mut HumanParser,
+    running: &mut Option<Tally>,
+) -> Vec<Event> {
+    match backend {
+        Backend::Human => human.abort(),
+        Backend::Json => running
+            .take()
+            .map(|tally| vec![Event::Suite(SuiteEvent::Failed(tally.result))])
+            .unwrap_or_default(),
+    }
+}
 
+/// What the running binary has reported so far.
+struct Tally {
+    test_count: usize,
+    result: SuiteResult,
+}
+
+impl Tally {
+    /// Count `event` towards the binary that is running, if any.
+    fn track(running: &mut Option<Tally>, event: &Event) {
         match event {
-            Event::Suite(suite) => match suite {
-                SuiteEvent::Started { test_count } => {
-                    formatter.suite_started(test_count, w)?;
+            Event::Suite(SuiteEvent::Started { test_count, .. }) => {
+                *running = Some(Tally {
+                    test_count: *test_count,
+                    result: SuiteResult::default(),
+                });
+            }
+            Event::Suite(_) => *running = None,
+            Event::Test(test) => {
+                let Some(tally) = running else {
+                    return;
+                };
+                match test {
+                    TestEvent::Ok { .. } | TestEvent::AllowedFail { .. } => {
+                        tally.result.passed += 1
+                    }
+                    TestEvent::Failed { .. } => tally.result.failed += 1,
+                    TestEvent::Ignored { .. } => tally.result.ignored += 1,
+                    TestEvent::Started { .. } | TestEvent::Timeout { .. } => {}
                 }
-                SuiteEvent::Ok(result) => {
-                    formatter.suite_finished(&result, true, w)?;
-                }
-                SuiteEvent::Failed(result) => {
-                    any_failure = true;
-                    formatter.suite_finished(&result, false, w)?;
+            }
+            Event::Bench(_) | Event::Report(_) => {}
+        }
+    }
+
+    /// Tests in the binary that haven't finished.
+    fn not_run(&self) -> usize {
+        let r = &self.result;
+        self.test_count
+            .saturating_sub(r.passed + r.failed + r.ignored)
+    }
+}
+
+/// Have `cmd` lead a process group of its own, so [`kill_tree`] reaches the
+/// test binary cargo starts as well as cargo.
+///
+/// The group is no longer the terminal's, so Ctrl-C only reaches the
+/// runner; a [`Forward`] passes it on.
+fn own_process_group(cmd: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(not(unix))]
+    let _ = cmd;
+}
+
+/// The process group a [`Forward`] kills on SIGINT or SIGTERM, or 0.
+#[cfg(unix)]
+static GROUP: AtomicI32 = AtomicI32::new(0);
+
+/// Kills the process group a child leads when the runner is interrupted or
+/// terminated, until dropped.
+///
+/// Without it, Ctrl-C during a `--fail-fast` run would leave cargo and the
+/// test binary running in their own group.
+struct Forward;
+
+impl Forward {
+    fn new(child: &Child) -> Forward {
+        #[cfg(unix)]
+        {
+            static INSTALL: Once = Once::new();
+            GROUP.store(child.id() as i32, Ordering::SeqCst);
+            INSTALL.call_once(|| {
+                let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+                // SAFETY: the handler only calls async-signal-safe functions
+                unsafe {
+                    libc::signal(libc::SIGINT, handler);
+                    libc::signal(libc::SIGTERM, handler);
                 }
-            },
-            Event::Test(test) => match test {
-                TestEvent::Started { ref name } => {
-                    formatter.test_started(name, w)?;
+            });
+        }
+        #[cfg(not(unix))]
+        let _ = child;
+        Forward
+    }
+}
+
+impl Drop for Forward {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        GROUP.store(0, Ordering::SeqCst);
+    }
+}
+
+/// Kill the group, then die of `signal` as if there were no handler.
+#[cfg(unix)]
+extern "C" fn on_signal(signal: libc::c_int) {
+    let group = GROUP.load(Ordering::SeqCst);
+    // SAFETY: kill, signal and raise are async-signal-safe
+    unsafe {
+        if group > 0 {
+            libc::kill(-group, libc::SIGKILL);
+        }
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
+    }
+}
+
+/// Stop cargo and the test binary it is running.
+fn kill_tree(child: &mut Child) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let group = -(child.id() as libc::pid_t);
+        // SAFETY: kill has no memory effects; the group is the one cargo leads
+        if unsafe { libc::kill(group, libc::SIGKILL) } == 0 {
+            return Ok(());
+        }
+    }
+    child.kill()
+}
+
+fn is_json_object(line: &str) -> bool {
+    line.starts_with('{') && serde_json::from_str::<serde_json::Value>(line).is_ok()
+}
+
+/// Hand one event to the formatter, recording finished suites under `label`.
+///
+/// With `backtraces`, the workspace root, the backtrace in a failure's output
+/// is cut down to the user's frames. Returns `true` for a failed suite.
+fn dispatch(
+    event: Event,
+    label: &str,
+    target: Option<&Target>,
+    backtraces: Option<&Path>,
+    summary: &mut RunSummary,
+    formatter: &mut dyn Formatter,
+    w: &mut dyn Write,
+) -> io::Result<bool> {
+    match event {
+        Event::Suite(suite) => match suite {
+            SuiteEvent::Started {
+                test_count,
+                shuffle_seed,
+            } => {
+                formatter.suite_started(test_count, w)?;
+                if let Some(seed) = shuffle_seed {
+                    formatter.suite_shuffled(seed, w)?;
                 }
-                TestEvent::Ok {
-                    ref name,
-                    exec_time,
-                } => {
-                    formatter.test_passed(name, exec_time, w)?;
+            }
+            SuiteEvent::Ok(result) => {
+                summary.push(label, target, &result);
+                formatter.suite_finished(&result, true, w)?;
+            }
+            SuiteEvent::Failed(result) => {
+                summary.push(label, target, &result);
+                formatter.suite_finished(&result, false, w)?;
+                return Ok(true);
+            }
+        },
+        Event::Test(test) => match test {
+            TestEvent::Started { ref name } => {
+                formatter.test_started(name, w)?;
+            }
+            TestEvent::Ok {
+                ref name,
+                exec_time,
+                ref stdout,
+            } => {
+                if let Some(t) = exec_time {
+                    summary.test_timed(name, t);
                 }
-                TestEvent::Failed {
-                    ref name,
-                    exec_time,
-                    ref stdout,
-                    ref message,
-                } => {
-                    formatter.test_failed(
-                        name,
-                        exec_time,
-                        stdout.as_deref(),
-                        message.as_deref(),
-                        w,
-                    )?;
+                formatter.test_passed(name, exec_time, w)?;
+                if let Some(stdout) = stdout {
+                    formatter.test_output(name, stdout, w)?;
                 }
-                TestEvent::Ignored { ref name } => {
-                    formatter.test_ignored(name, w)?;
+            }
+            TestEvent::Failed {
+                ref name,
+                exec_time,
+                ref stdout,
+                ref message,
+                ref reason,
+            } => {
+                summary.test_failed(name);
+                if let Some(t) = exec_time {
+                    summary.test_timed(name, t);
                 }
-            },
+                let stdout = match (stdout, target, backtraces) {
+                    (Some(stdout), Some(target), Some(root)) => Some(backtrace::filter(
+                        stdout,
+                        Path::new(&target.manifest_path),
+                        root,
+                    )),
+                    _ => stdout.clone(),
+                };
+                let message = message.as_deref().or(reason.as_deref());
+                formatter.test_failed(name, exec_time, stdout.as_deref(), message, w)?;
+            }
+            TestEvent::Ignored {
+                ref name,
+                ref message,
+            } => {
+                formatter.test_ignored_with_message(name, message.as_deref(), w)?;
+            }
+            TestEvent::AllowedFail { ref name } => {
+                formatter.test_allowed_fail(name, w)?;
+            }
+            TestEvent::Timeout { ref name } => {
+                formatter.test_timeout(name, w)?;
+            }
+        },
+        Event::Bench(ref bench) => {
+            formatter.bench_result(bench, w)?;
+        }
+        Event::Report(ref times) => {
+            formatter.doc_test_times(times, w)?;
         }
     }
+    Ok(false)
+}
 
-    let status = child.wait()?;
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Stdio;
+    use std::time::Instant;
 
-    if any_failure || !status.success() {
-        Ok(ExitCode::FAILURE)
-    } else {
-        Ok(ExitCode::SUCCESS)
+    /// Set in the copy of the test binary that plays the runner.
+    const PLAY_RUNNER: &str = "SPECTACULAR_PLAY_RUNNER";
+
+    /// Whether `pid` has ended; a zombie nobody reaped yet counts as ended.
+    fn ended(pid: &str) -> bool {
+        match std::fs::read_to_string(format!("/proc/{pid}/stat")) {
+            Ok(stat) => stat
+                .rsplit_once(") ")
+                .is_some_and(|(_, rest)| rest.starts_with('Z')),
+            Err(_) => true,
+        }
+    }
+
+    #[test]
+    fn an_interrupt_kills_the_process_group() {
+        if std::env::var_os(PLAY_RUNNER).is_some() {
+            // The runner: a child in its own group, with a grandchild like
+            // the test binary cargo starts
+            let mut cmd = Command::new("sh");
+            cmd.args(["-c", "sleep 30 & echo \"grandchild $!\"; wait"]);
+            own_process_group(&mut cmd);
+            let mut child = cmd.spawn().unwrap();
+            let _forward = Forward::new(&child);
+            child.wait().unwrap();
+            return;
+        }
+
+        let exe = std::env::current_exe().unwrap();
+        let mut runner = Command::new(exe)
+            .args([
+                "--exact",
+                "runner::tests::an_interrupt_kills_the_process_group",
+            ])
+            .args(["--nocapture", "--test-threads", "1"])
+            .env(PLAY_RUNNER, "1")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let stdout = BufReader::new(runner.stdout.take().unwrap());
+        // libtest has printed the test's name on the same line
+        let grandchild = stdout
+            .lines()
+            .map_while(Result::ok)
+            .find_map(|line| Some(line.split_once("grandchild ")?.1.to_string()))
+            .unwrap();
+        // Let the runner reach its handler's install
+        thread::sleep(Duration::from_millis(200));
+
+        // SAFETY: kill has no memory effects
+        unsafe { libc::kill(runner.id() as libc::pid_t, libc::SIGINT) };
+        let status = runner.wait().unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !ended(&grandchild) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(status.signal(), Some(libc::SIGINT));
+        assert!(ended(&grandchild), "process {grandchild} is still running");
     }
 }
@@ -0,0 +1,296 @@
+use crate::meta::TestMeta;
+
+/// libtest options that consume the following argument as their value.
+const VALUE_OPTIONS: &[&str] = &[
+    "--skip",
+    "--test-threads",
+    "--logfile",
+    "--color",
+    "--format",
+    "--shuffle-seed",
+    "-Z",
+];
+
+/// Extra test arguments split into name filters and everything else.
+///
+/// When the runner picks tests itself it passes exact names, so the user's
+/// own substring filters and `--skip`s are applied here instead of by libtest.
+#[derive(Debug, Default)]
+pub struct TestArgs {
+    pub filters: Vec<String>,
+    pub skips: Vec<String>,
+    pub exact: bool,
+    pub rest: Vec<String>,
+}
+
+impl TestArgs {
+    pub fn parse(args: &[String]) -> Self {
+        let mut parsed = TestArgs::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--exact" => parsed.exact = true,
+                "--skip" => parsed.skips.extend(iter.next().cloned()),
+                a if VALUE_OPTIONS.contains(&a) => {
+                    parsed.rest.push(arg.clone());
+                    parsed.rest.extend(iter.next().cloned());
+                }
+                a if a.starts_with("--skip=") => parsed.skips.push(a["--skip=".len()..].into()),
+                a if a.starts_with('-') => parsed.rest.push(arg.clone()),
+                _ => parsed.filters.push(arg.clone()),
+            }
+        }
+        parsed
+    }
+
+    /// Whether libtest would run `name` given these filters and skips.
+    pub fn matches(&self, name: &str) -> bool {
+        let hit = |pattern: &String| {
+            if self.exact {
+                name == pattern
+            } else {
+                name.contains(pattern.as_str())
+            }
+        };
+        (self.filters.is_empty() || self.filters.iter().any(hit)) && !self.skips.iter().any(hit)
+    }
+}
+
+/// Whether a test with `tags` passes `--tag` (any of) and `--exclude-tag` (none of).
+pub fn tags_match(tags: &[String], include: &[String], exclude: &[String]) -> bool {
+    (include.is_empty() || include.iter().any(|t| tags.contains(t)))
+        && !exclude.iter().any(|t| tags.contains(t))
+}
+
+/// Pick the tests of one binary that pass the user's filters and the tag rules.
+///
+/// Tests without spectacular metadata (plain `#[test]`s) have no tags.
+pub fn by_tags(
+    names: Vec<String>,
+    metas: &[TestMeta],
+    args: &TestArgs,
+    include: &[String],
+    exclude: &[String],
+) -> Vec<String> {
+    names
+        .into_iter()
+        .filter(|name| args.matches(name))
+        .filter(|name| {
+            let tags = metas
+                .iter()
+                .find(|m| m.name() == name)
+                .map_or(&[][..], |m| m.tags.as_slice());
+            tags_match(tags, include, exclude)
+        })
+        .collect()
+}
+
+/// One of `count` disjoint slices of the suite, for splitting a run across machines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    /// 1-based, as written in `--shard K/N`.
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Shard {
+    /// Parse `K/N`, with `1 <= K <= N`.
+    pub fn parse(value: &str) -> Option<Shard> {
+        let (index, count) = value.split_once('/')?;
+        let index: usize = index.trim().parse().ok()?;
+        let count: usize = count.trim().parse().ok()?;
+        (1..=count)
+            .contains(&index)
+            .then_some(Shard { index, count })
+    }
+
+    /// Whether the test `name` of the target labelled `target` belongs to this shard.
+    ///
+    /// Depends only on the two names, so every machine splits the suite the
+    /// same way, whatever it ran before and wherever the checkout lives.
+    pub fn contains(&self, target: &str, name: &str) -> bool {
+        let hash = stable_hash(&format!("{target}:{name}"));
+        hash % self.count as u64 == (self.index - 1) as u64
+    }
+}
+
+impl std::fmt::Display for Shard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+/// Bytes of test names one `cargo test` may be given, well inside Windows'
+/// 32K command line once the paths and other arguments are added.
+pub const ARGS_LIMIT: usize = 24 * 1024;
+
+/// Split `names` into batches whose arguments stay within `limit` bytes, so
+/// a large selection runs as several `cargo test`s instead of failing to start.
+///
+/// A name longer than `limit` on its own still gets a batch of its own.
+pub fn batches(names: Vec<String>, limit: usize) -> Vec<Vec<String>> {
+    let mut batches: Vec<Vec<String>> = Vec::new();
+    let mut size = 0;
+    for name in names {
+        // The separating space, or the quotes Windows may add around it
+        let cost = name.len() + 3;
+        match batches.last_mut() {
+            Some(batch) if size + cost <= limit => batch.push(name),
+            _ => {
+                size = 0;
+                batches.push(vec![name]);
+            }
+        }
+        size += cost;
+    }
+    batches
+}
+
+/// FNV-1a: the same on every platform and toolchain, unlike `DefaultHasher`.
+pub fn stable_hash(s: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in s.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(count: usize) -> Vec<String> {
+        (0..count)
+            .map(|i| format!("group_{}::test_{i}", i % 7))
+            .collect()
+    }
+
+    #[test]
+    fn stable_hash_is_fnv_1a() {
+        assert_eq!(stable_hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(stable_hash("foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn shards_split_the_same_way_every_time() {
+        let shard = Shard::parse("2/3").unwrap();
+        let first: Vec<bool> = names(200)
+            .iter()
+            .map(|n| shard.contains("auth", n))
+            .collect();
+        let again: Vec<bool> = names(200)
+            .iter()
+            .map(|n| shard.contains("auth", n))
+            .collect();
+        assert_eq!(first, again);
+        // Pinned, so a change to the split shows up here before it reaches CI
+        assert!(
+            Shard::parse("1/3")
+                .unwrap()
+                .contains("auth", "group_0::test_0")
+        );
+    }
+
+    #[test]
+    fn shards_cover_every_test_exactly_once() {
+        for count in 1..=8 {
+            let shards: Vec<Shard> = (1..=count).map(|index| Shard { index, count }).collect();
+            for target in ["auth", "billing (bin)", "demo doc-tests"] {
+                for name in names(300) {
+                    let owners = shards.iter().filter(|s| s.contains(target, &name)).count();
+                    assert_eq!(owners, 1, "{target} {name} in {count} shards");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shards_are_roughly_even() {
+        let count = 4;
+        for index in 1..=count {
+            let shard = Shard { index, count };
+            let mine = names(2000)
+                .iter()
+                .filter(|n| shard.contains("auth", n))
+                .count();
+            assert!(
+                (400..600).contains(&mine),
+                "shard {shard} got {mine} of 2000"
+            );
+        }
+    }
+
+    #[test]
+    fn shard_parse_checks_the_range() {
+        assert_eq!(Shard::parse(" 3 / 8 "), Some(Shard { index: 3, count: 8 }));
+        assert_eq!(Shard::parse("0/8"), None);
+        assert_eq!(Shard::parse("9/8"), None);
+        assert_eq!(Shard::parse("1/0"), None);
+        assert_eq!(Shard::parse("1"), None);
+    }
+
+    #[test]
+    fn batches_stay_within_the_limit() {
+        let all = names(5000);
+        let batches = batches(all.clone(), 1000);
+        assert!(batches.len() > 1);
+        for batch in &batches {
+            let size: usize = batch.iter().map(|n| n.len() + 3).sum();
+            assert!(size <= 1000, "{size}");
+        }
+        assert_eq!(batches.concat(), all);
+    }
+
+    #[test]
+    fn an_overlong_name_gets_a_batch_of_its_own() {
+        let long = "x".repeat(50);
+        let batches = batches(vec![String::from("a"), long.clone(), String::from("b")], 10);
+        assert_eq!(
+            batches,
+            [vec![String::from("a")], vec![long], vec![String::from("b")]]
+        );
+        assert!(super::batches(Vec::new(), 10).is_empty());
+    }
+
+    #[test]
+    fn test_args_split_filters_from_options() {
+        let args: Vec<String> = [
+            "auth",
+            "--skip",
+            "slow",
+            "--test-threads",
+            "2",
+            "--nocapture",
+        ]
+        .map(String::from)
+        .to_vec();
+        let parsed = TestArgs::parse(&args);
+        assert_eq!(parsed.filters, ["auth"]);
+        assert_eq!(parsed.skips, ["slow"]);
+        assert_eq!(parsed.rest, ["--test-threads", "2", "--nocapture"]);
+        assert!(parsed.matches("auth::login"));
+        assert!(!parsed.matches("auth::slow_login"));
+        assert!(!parsed.matches("billing::charge"));
+    }
+
+    #[test]
+    fn exact_filters_match_whole_names() {
+        let args: Vec<String> = ["--exact", "auth::login"].map(String::from).to_vec();
+        let parsed = TestArgs::parse(&args);
+        assert!(parsed.matches("auth::login"));
+        assert!(!parsed.matches("auth::login_twice"));
+    }
+
+    #[test]
+    fn tags_include_any_and_exclude_all() {
+        let tags = [String::from("slow"), String::from("db")];
+        let t = |s: &[&str]| s.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert!(tags_match(&tags, &[], &[]));
+        assert!(tags_match(&tags, &t(&["net", "db"]), &[]));
+        assert!(!tags_match(&tags, &t(&["net"]), &[]));
+        assert!(!tags_match(&tags, &[], &t(&["slow"])));
+        assert!(!tags_match(&[], &t(&["slow"]), &[]));
+    }
+}
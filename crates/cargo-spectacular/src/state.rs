@@ -0,0 +1,82 @@
+use crate::artifacts;
+use crate::runner::RunConfig;
+use crate::summary::Rerun;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The last run's outcome, kept for `--only-failures`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LastRun {
+    failures: Vec<Rerun>,
+}
+
+/// Where the runner keeps files between runs: `<target dir>/spectacular`.
+pub fn dir(config: &RunConfig) -> io::Result<PathBuf> {
+    Ok(artifacts::workspace(config)?
+        .target_directory
+        .join("spectacular"))
+}
+
+/// Record the failed tests of the run that just finished, replacing the last one.
+pub fn save_failures(dir: &Path, failures: &[Rerun]) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let last_run = LastRun {
+        failures: failures.to_vec(),
+    };
+    let json = serde_json::to_string_pretty(&last_run)?;
+    fs::write(dir.join("last-run.json"), json + "\n")
+}
+
+/// The failed tests of the last run, or `None` when no run was recorded.
+pub fn load_failures(dir: &Path) -> io::Result<Option<Vec<Rerun>>> {
+    let path = dir.join("last-run.json");
+    let json = match fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let last_run: LastRun = serde_json::from_str(&json).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    })?;
+    Ok(Some(last_run.failures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifacts::Target;
+    use crate::testing::scratch;
+
+    #[test]
+    fn failures_round_trip() {
+        let dir = scratch("state-round-trip");
+        let failures = vec![Rerun {
+            target: Target::test("auth"),
+            tests: vec![String::from("auth::login")],
+        }];
+        let before = load_failures(&dir).unwrap();
+        save_failures(&dir, &failures).unwrap();
+        let after = load_failures(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(before, None);
+        assert_eq!(after, Some(failures));
+    }
+
+    #[test]
+    fn a_damaged_file_names_its_path() {
+        let dir = scratch("state-damaged");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("last-run.json"), "{").unwrap();
+        let err = load_failures(&dir).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("last-run.json"), "{err}");
+    }
+}
@@ -0,0 +1,198 @@
+use crate::artifacts::Target;
+use crate::event::SuiteResult;
+use crate::select::Shard;
+use serde::{Deserialize, Serialize};
+
+/// Results of one test binary, labelled by its cargo target.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct BinarySummary {
+    pub label: String,
+    /// The target to run again, when the binary could be identified.
+    pub target: Option<Target>,
+    pub result: SuiteResult,
+    /// Names of the tests that failed.
+    pub failed: Vec<String>,
+    /// How long each test took, for the tests libtest timed.
+    pub durations: Vec<(String, f64)>,
+}
+
+/// Tests to run again, all from one target.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rerun {
+    pub target: Target,
+    pub tests: Vec<String>,
+}
+
+/// The part of the suite a sharded run covered.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct ShardRun {
+    pub shard: Shard,
+    /// Tests in this shard, of the `total` that passed the other filters.
+    pub selected: usize,
+    pub total: usize,
+}
+
+/// Results of every test binary in a run, in the order they ran.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RunSummary {
+    pub binaries: Vec<BinarySummary>,
+    /// Set when the run was one shard of the suite.
+    pub shard: Option<ShardRun>,
+    /// Set when `--fail-fast` stopped the run: how many of the selected tests
+    /// never ran. Doc-tests can't be counted ahead of time and aren't included.
+    pub not_run: Option<usize>,
+    /// Failures in the binary that is still running.
+    failing: Vec<String>,
+    /// Test durations in the binary that is still running.
+    timing: Vec<(String, f64)>,
+}
+
+impl RunSummary {
+    pub(crate) fn test_failed(&mut self, name: &str) {
+        self.failing.push(name.to_string());
+    }
+
+    pub(crate) fn test_timed(&mut self, name: &str, exec_time: f64) {
+        self.timing.push((name.to_string(), exec_time));
+    }
+
+    /// Record a finished binary, with the failures and durations seen since the last one.
+    pub(crate) fn push(&mut self, label: &str, target: Option<&Target>, result: &SuiteResult) {
+        self.binaries.push(BinarySummary {
+            label: label.to_string(),
+            target: target.cloned(),
+            result: result.clone(),
+            failed: std::mem::take(&mut self.failing),
+            durations: std::mem::take(&mut self.timing),
+        });
+    }
+
+    /// Stop the run early, with `count` more tests that won't run.
+    pub(crate) fn stop(&mut self, count: usize) {
+        *self.not_run.get_or_insert(0) += count;
+    }
+
+    /// Whether `--fail-fast` stopped the run.
+    pub fn stopped(&self) -> bool {
+        self.not_run.is_some()
+    }
+
+    /// The grand total across all binaries.
+    pub fn total(&self) -> SuiteResult {
+        let mut total = SuiteResult::default();
+        for binary in &self.binaries {
+            let r = &binary.result;
+            total.passed += r.passed;
+            total.failed += r.failed;
+            total.ignored += r.ignored;
+            total.measured += r.measured;
+            total.filtered_out += r.filtered_out;
+            if let Some(t) = r.exec_time {
+                *total.exec_time.get_or_insert(0.0) += t;
+            }
+        }
+        total
+    }
+
+    /// The failed tests, grouped by the target to run them from.
+    pub(crate) fn failures(&self) -> Vec<Rerun> {
+        let mut reruns: Vec<Rerun> = Vec::new();
+        for binary in &self.binaries {
+            let Some(ref target) = binary.target else {
+                continue;
+            };
+            if binary.failed.is_empty() {
+                continue;
+            }
+            match reruns.iter_mut().find(|r| r.target == *target) {
+                Some(rerun) => rerun.tests.extend(binary.failed.iter().cloned()),
+                None => reruns.push(Rerun {
+                    target: target.clone(),
+                    tests: binary.failed.clone(),
+                }),
+            }
+        }
+        reruns
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(passed: usize, failed: usize, exec_time: Option<f64>) -> SuiteResult {
+        SuiteResult {
+            passed,
+            failed,
+            exec_time,
+            ..SuiteResult::default()
+        }
+    }
+
+    #[test]
+    fn each_binary_takes_the_failures_and_durations_since_the_last() {
+        let mut summary = RunSummary::default();
+        summary.test_timed("login", 0.5);
+        summary.test_failed("login");
+        summary.push("auth", Some(&Target::test("auth")), &result(0, 1, None));
+        summary.test_timed("parse", 0.1);
+        summary.push("parse", Some(&Target::test("parse")), &result(1, 0, None));
+
+        assert_eq!(summary.binaries[0].failed, ["login"]);
+        assert_eq!(
+            summary.binaries[0].durations,
+            [(String::from("login"), 0.5)]
+        );
+        assert!(summary.binaries[1].failed.is_empty());
+        assert_eq!(
+            summary.binaries[1].durations,
+            [(String::from("parse"), 0.1)]
+        );
+    }
+
+    #[test]
+    fn total_adds_up_the_binaries() {
+        let mut summary = RunSummary::default();
+        summary.push("auth", None, &result(2, 1, Some(0.25)));
+        summary.push("parse", None, &result(3, 0, None));
+        summary.push("doc-tests", None, &result(1, 0, Some(0.5)));
+
+        let total = summary.total();
+        assert_eq!((total.passed, total.failed), (6, 1));
+        assert_eq!(total.exec_time, Some(0.75));
+        assert_eq!(RunSummary::default().total().exec_time, None);
+    }
+
+    #[test]
+    fn failures_are_grouped_by_target() {
+        let mut summary = RunSummary::default();
+        summary.test_failed("a");
+        summary.push("auth", Some(&Target::test("auth")), &result(0, 1, None));
+        summary.test_failed("lost");
+        summary.push("crashed", None, &result(0, 1, None));
+        summary.test_failed("b");
+        summary.push("auth", Some(&Target::test("auth")), &result(0, 1, None));
+
+        assert_eq!(
+            summary.failures(),
+            [Rerun {
+                target: Target::test("auth"),
+                tests: vec![String::from("a"), String::from("b")],
+            }]
+        );
+    }
+
+    #[test]
+    fn stopping_counts_the_tests_left() {
+        let mut summary = RunSummary::default();
+        assert!(!summary.stopped());
+        summary.stop(3);
+        summary.stop(0);
+        summary.stop(2);
+        assert!(summary.stopped());
+        assert_eq!(summary.not_run, Some(5));
+    }
+}
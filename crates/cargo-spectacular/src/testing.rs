@@ -0,0 +1,24 @@
+//! Fixtures shared by the unit tests.
+
+use crate::artifacts::{Target, TargetKind};
+use std::fs;
+use std::path::PathBuf;
+
+impl Target {
+    /// The integration test target `name` of a package at `/work`.
+    pub(crate) fn test(name: &str) -> Target {
+        Target {
+            manifest_path: String::from("/work/Cargo.toml"),
+            kind: TargetKind::Test,
+            name: name.to_string(),
+        }
+    }
+}
+
+/// A fresh, empty directory for the test named `test`.
+pub(crate) fn scratch(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("spectacular-{test}-{}", std::process::id()));
+    drop(fs::remove_dir_all(&dir));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,63 @@
+use std::io::{self, Write};
+
+/// Where a test sits in the tree of its binary.
+pub(crate) struct Node<'a> {
+    /// The module path of the test within its binary; empty at the top.
+    pub(crate) module: &'a str,
+    /// The `describe` text of that module, when spectacular embedded it.
+    pub(crate) group: Option<&'a str>,
+    /// The line the test is declared on.
+    pub(crate) line: Option<u32>,
+    /// The libtest name, which orders tests declared on the same line.
+    pub(crate) name: &'a str,
+}
+
+/// Write `tests` as an indented tree of their groups, in declaration order.
+///
+/// Each group is headed by its `describe` text, from any of its own tests,
+/// or else by its module name. `write_test` writes a test's line after its
+/// indent.
+pub(crate) fn write<'a, T>(
+    tests: &'a [T],
+    node: impl Fn(&'a T) -> Node<'a>,
+    mut write_test: impl FnMut(&T, &mut dyn Write) -> io::Result<()>,
+    w: &mut dyn Write,
+) -> io::Result<()> {
+    let mut rows: Vec<(Node<'a>, &T)> = tests.iter().map(|t| (node(t), t)).collect();
+    rows.sort_by(|(a, _), (b, _)| {
+        a.module
+            .split("::")
+            .cmp(b.module.split("::"))
+            .then_with(|| a.line.unwrap_or(u32::MAX).cmp(&b.line.unwrap_or(u32::MAX)))
+            .then_with(|| a.name.cmp(b.name))
+    });
+
+    let mut open: Vec<&str> = Vec::new();
+    for (i, (node, test)) in rows.iter().enumerate() {
+        let groups: Vec<&str> = if node.module.is_empty() {
+            Vec::new()
+        } else {
+            node.module.split("::").collect()
+        };
+
+        let shared = open.iter().zip(&groups).take_while(|(a, b)| a == b).count();
+        for (depth, segment) in groups.iter().enumerate().skip(shared) {
+            let path = groups[..=depth].join("::");
+            let label = rows[i..]
+                .iter()
+                .filter(|(n, _)| n.module == path)
+                .find_map(|(n, _)| n.group)
+                .unwrap_or(segment);
+            writeln!(w, "{}{label}", indent(depth))?;
+        }
+
+        write!(w, "{}", indent(groups.len()))?;
+        write_test(test, w)?;
+        open = groups;
+    }
+    Ok(())
+}
+
+fn indent(depth: usize) -> String {
+    "  ".repeat(depth + 1)
+}
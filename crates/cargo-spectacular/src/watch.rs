@@ -0,0 +1,254 @@
+use crate::artifacts;
+use crate::formatter::Formatter;
+use crate::runner::{self, RunConfig};
+use crate::summary::Rerun;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, terminal};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// Quiet time after the last file change before the tests run again.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// How often to check for keypresses while waiting.
+const POLL: Duration = Duration::from_millis(100);
+
+/// What to do after waiting.
+enum Trigger {
+    /// Files changed, or the user asked for everything again.
+    All,
+    /// Run only the failures of the previous run.
+    Failures,
+    Quit,
+}
+
+/// Run the tests, then again whenever the workspace's sources change.
+///
+/// Watches `src/`, `tests/` and `Cargo.toml` of every workspace package.
+/// Each run gets a fresh formatter from `make_formatter`. Between runs,
+/// `f` re-runs only the last failures, `Enter` or `a` runs everything
+/// and `q` quits.
+pub fn watch(
+    config: &RunConfig,
+    make_formatter: impl Fn() -> io::Result<Box<dyn Formatter>>,
+) -> io::Result<ExitCode> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).map_err(io::Error::other)?;
+    for path in watched_paths(config)? {
+        let mode = if path.is_dir() {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        watcher.watch(&path, mode).map_err(io::Error::other)?;
+    }
+
+    let interactive = io::stdin().is_terminal();
+    let mut only = config.only.clone();
+    let mut failures: Vec<Rerun> = Vec::new();
+
+    loop {
+        clear()?;
+        let run_config = RunConfig {
+            only: only.take(),
+            ..config.clone()
+        };
+        let mut formatter = make_formatter()?;
+        let result = runner::run(&run_config, formatter.as_mut(), &mut io::stdout().lock());
+        match result {
+            // A broken build says nothing about which tests fail
+            Ok(result) if result.status != runner::Status::BuildFailed => {
+                failures = result.summary.failures();
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Error: {e}"),
+        }
+
+        print_prompt(interactive, !failures.is_empty())?;
+        match wait(&rx, interactive, !failures.is_empty())? {
+            Trigger::All => {}
+            Trigger::Failures => only = Some(failures.clone()),
+            Trigger::Quit => return Ok(ExitCode::SUCCESS),
+        }
+    }
+}
+
+/// `src/`, `tests/` and `Cargo.toml` of every package, plus the workspace manifest.
+fn watched_paths(config: &RunConfig) -> io::Result<Vec<PathBuf>> {
+    let metadata = artifacts::workspace(config)?;
+
+    let mut paths = vec![metadata.workspace_root.join("Cargo.toml")];
+    for package in &metadata.packages {
+        let Some(dir) = package.manifest_path.parent() else {
+            continue;
+        };
+        paths.push(package.manifest_path.clone());
+        paths.push(dir.join("src"));
+        paths.push(dir.join("tests"));
+    }
+    paths.sort();
+    paths.dedup();
+    paths.retain(|p| p.exists());
+    Ok(paths)
+}
+
+/// Clear the screen before a run, or separate runs with a rule when it isn't a terminal.
+fn clear() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    if stdout.is_terminal() {
+        execute!(
+            stdout,
+            terminal::Clear(terminal::ClearType::All),
+            terminal::Clear(terminal::ClearType::Purge),
+            cursor::MoveTo(0, 0)
+        )
+    } else {
+        writeln!(stdout, "\n{}\n", "─".repeat(60))
+    }
+}
+
+fn print_prompt(interactive: bool, has_failures: bool) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "\nWatching for changes.")?;
+    if interactive {
+        if has_failures {
+            write!(stdout, " Press f to re-run failures,")?;
+        } else {
+            write!(stdout, " Press")?;
+        }
+        write!(stdout, " Enter to re-run all, q to quit.")?;
+    }
+    writeln!(stdout)?;
+    stdout.flush()
+}
+
+/// Wait for a relevant file change or a keypress; `f` is ignored when there
+/// are no failures to re-run.
+fn wait(
+    rx: &Receiver<notify::Result<notify::Event>>,
+    interactive: bool,
+    has_failures: bool,
+) -> io::Result<Trigger> {
+    // Raw mode only while waiting, so keys arrive unbuffered and the
+    // formatters' output is untouched
+    let _raw = interactive.then(RawMode::enable).transpose()?;
+    let mut changed: Option<Instant> = None;
+
+    loop {
+        while let Ok(event) = rx.try_recv() {
+            if let Ok(event) = event
+                && is_relevant(&event)
+            {
+                changed = Some(Instant::now());
+            }
+        }
+        if changed.is_some_and(|at| at.elapsed() >= DEBOUNCE) {
+            return Ok(Trigger::All);
+        }
+
+        if !interactive {
+            std::thread::sleep(POLL);
+            continue;
+        }
+        if !event::poll(POLL)? {
+            continue;
+        }
+        if let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        {
+            match code {
+                KeyCode::Char('f') if has_failures => return Ok(Trigger::Failures),
+                KeyCode::Char('a') | KeyCode::Enter => return Ok(Trigger::All),
+                KeyCode::Char('q') => return Ok(Trigger::Quit),
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(Trigger::Quit);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Whether a file event should trigger a run: content changes, not reads,
+/// and not editor swap or backup files.
+fn is_relevant(event: &notify::Event) -> bool {
+    let changes = matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    );
+    changes && event.paths.iter().any(|p| !is_scratch_file(p))
+}
+
+fn is_scratch_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy())
+        .unwrap_or_default();
+    name.starts_with('.') || name.ends_with('~') || name.ends_with(".swp")
+}
+
+/// Terminal raw mode, restored when dropped.
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, ModifyKind};
+
+    fn event(kind: EventKind, path: &str) -> notify::Event {
+        notify::Event::new(kind).add_path(PathBuf::from(path))
+    }
+
+    #[test]
+    fn changes_to_source_files_trigger_a_run() {
+        assert!(is_relevant(&event(
+            EventKind::Modify(ModifyKind::Any),
+            "/work/src/lib.rs"
+        )));
+        assert!(is_relevant(&event(
+            EventKind::Create(CreateKind::File),
+            "/work/tests/auth.rs"
+        )));
+        assert!(!is_relevant(&event(
+            EventKind::Access(AccessKind::Any),
+            "/work/src/lib.rs"
+        )));
+    }
+
+    #[test]
+    fn editor_scratch_files_do_not() {
+        for path in [
+            "/work/src/.lib.rs.swp",
+            "/work/src/lib.rs~",
+            "/work/src/4913.swp",
+        ] {
+            assert!(is_scratch_file(Path::new(path)), "{path}");
+            assert!(!is_relevant(&event(
+                EventKind::Modify(ModifyKind::Any),
+                path
+            )));
+        }
+        assert!(!is_scratch_file(Path::new("/work/src/lib.rs")));
+    }
+}
@@ -1,12 +1,25 @@
 //! Attribute-style `#[test_suite]` — parsing and code generation.
 
 use quote::quote;
+use syn::punctuated::Punctuated;
 use syn::{ItemFn, ItemMod};
 
 use crate::{
-    Runtime, default_runtime, is_type_infer, ref_inner_type, wrap_async_test_body, wrap_test_body,
+    Runtime, TestMeta, default_runtime, is_type_infer, merge_tags, ref_inner_type,
+    wrap_async_test_body, wrap_bench_body, wrap_test_body,
 };
 
+/// Collect tags from every `#[tags(...)]` attribute in `attrs`.
+fn extract_tags(attrs: &[syn::Attribute]) -> syn::Result<Vec<String>> {
+    let mut tags = Vec::new();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("tags")) {
+        let idents =
+            attr.parse_args_with(Punctuated::<syn::Ident, syn::Token![,]>::parse_terminated)?;
+        tags.extend(idents.iter().map(|t| t.to_string()));
+    }
+    Ok(tags)
+}
+
 /// Extract a meaningful return type from a function signature.
 /// Returns `None` for `()`, default return, or empty tuple.
 fn extract_return_type(func: &ItemFn) -> Option<syn::Type> {
@@ -51,6 +64,27 @@ fn extract_params(func: &ItemFn) -> Vec<Param> {
         .collect()
 }
 
+fn is_bench(func: &ItemFn) -> bool {
+    func.attrs.iter().any(|a| a.path().is_ident("bench"))
+}
+
+/// The `Bencher` parameter a `#[bench]` function takes first.
+fn bencher_param(func: &ItemFn) -> Option<&syn::PatType> {
+    match func.sig.inputs.first() {
+        Some(syn::FnArg::Typed(pat_type)) if is_bench(func) => Some(pat_type),
+        _ => None,
+    }
+}
+
+/// The context parameters of a test or benchmark, without a benchmark's `Bencher`.
+fn test_params(func: &ItemFn) -> Vec<Param> {
+    let mut params = extract_params(func);
+    if bencher_param(func).is_some() {
+        params.remove(0);
+    }
+    params
+}
+
 pub(crate) fn expand(
     input: ItemMod,
     has_suite: bool,
@@ -58,6 +92,8 @@ pub(crate) fn expand(
 ) -> syn::Result<proc_macro2::TokenStream> {
     let mod_name = &input.ident;
     let vis = &input.vis;
+    let group = mod_name.to_string();
+    let group_tags = extract_tags(&input.attrs)?;
 
     let Some((_, items)) = &input.content else {
         return Err(syn::Error::new_spanned(
@@ -110,7 +146,7 @@ pub(crate) fn expand(
                             ));
                         }
                         after_each_fn = Some(func);
-                    } else if attr.path().is_ident("test") {
+                    } else if attr.path().is_ident("test") || attr.path().is_ident("bench") {
                         is_test = true;
                     }
                 }
@@ -143,6 +179,22 @@ pub(crate) fn expand(
         return Err(syn::Error::new_spanned(&input.ident, msg));
     }
 
+    // Validate: benchmarks take the bencher and time a sync routine
+    for f in test_fns.iter().filter(|f| is_bench(f)) {
+        if f.sig.asyncness.is_some() {
+            return Err(syn::Error::new_spanned(
+                &f.sig,
+                "#[bench] functions must be sync (the measured routine runs in a loop)",
+            ));
+        }
+        if bencher_param(f).is_none() {
+            return Err(syn::Error::new_spanned(
+                &f.sig,
+                "#[bench] functions take the bencher first: `fn name(b: &mut Bencher)`",
+            ));
+        }
+    }
+
     // Validate: before/after (run-once) must be sync
     if let Some(f) = before_fn
         && f.sig.asyncness.is_some()
@@ -206,7 +258,7 @@ pub(crate) fn expand(
         before_return_type = find_ref(&after_params)
             .or_else(|| find_ref(&before_each_params))
             .or_else(|| find_ref(&after_each_params))
-            .or_else(|| test_fns.iter().find_map(|f| find_ref(&extract_params(f))));
+            .or_else(|| test_fns.iter().find_map(|f| find_ref(&test_params(f))));
         before_type_was_inferred = before_return_type.is_some();
     } else {
         before_type_was_inferred = false;
@@ -218,7 +270,7 @@ pub(crate) fn expand(
     // Detect inline mode from consumers: tests or after_each have `_`-typed params
     let has_infer_consumers = test_fns
         .iter()
-        .any(|f| extract_params(f).iter().any(|p| is_type_infer(&p.ty)))
+        .any(|f| test_params(f).iter().any(|p| is_type_infer(&p.ty)))
         || after_each_needs_inline;
     let before_each_needs_inline = !has_before_each_ctx && has_infer_consumers;
 
@@ -294,16 +346,29 @@ pub(crate) fn expand(
             let other_attrs: Vec<_> = func
                 .attrs
                 .iter()
-                .filter(|a| !a.path().is_ident("test"))
+                .filter(|a| {
+                    !a.path().is_ident("test")
+                        && !a.path().is_ident("bench")
+                        && !a.path().is_ident("tags")
+                })
                 .collect();
+            let tags = merge_tags(&group_tags, &extract_tags(&func.attrs)?);
 
             // Extract test params
-            let test_params = extract_params(func);
+            let test_params = test_params(func);
 
             // Determine if this specific test needs async wrapping
             let test_needs_async = is_async || (before_each_is_async || after_each_is_async);
 
-            let mut pre = proc_macro2::TokenStream::new();
+            let mut pre = TestMeta {
+                fn_name,
+                description: None,
+                group: &group,
+                tags: &tags,
+                span: fn_name.span(),
+                bench: is_bench(func),
+            }
+            .embed();
             let mut post = proc_macro2::TokenStream::new();
 
             // --- Suite before ---
@@ -478,6 +543,12 @@ pub(crate) fn expand(
                     { #(#ref_bindings)* #(#stmts)* }
                 }
             };
+            let body_with_bindings = match bencher_param(func) {
+                Some(bencher) => {
+                    wrap_bench_body(fn_name, &bencher.pat, Some(&bencher.ty), body_with_bindings)
+                }
+                None => body_with_bindings,
+            };
 
             // --- after_each ---
             if let Some(name) = after_each_name {
@@ -572,26 +643,26 @@ pub(crate) fn expand(
                 let test_attr = rt.test_attr();
                 let inner = wrap_async_test_body(pre, body_with_bindings, post, needs_catch);
 
-                quote! {
+                Ok(quote! {
                     #(#other_attrs)*
                     #test_attr
                     #fn_vis async fn #fn_name() {
                         #inner
                     }
-                }
+                })
             } else {
                 let inner = wrap_test_body(pre, body_with_bindings, post, needs_catch);
 
-                quote! {
+                Ok(quote! {
                     #(#other_attrs)*
                     #[test]
                     #fn_vis fn #fn_name() {
                         #inner
                     }
-                }
+                })
             }
         })
-        .collect();
+        .collect::<syn::Result<_>>()?;
 
     Ok(quote! {
         #vis mod #mod_name {
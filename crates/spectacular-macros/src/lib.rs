@@ -9,6 +9,7 @@ mod spec;
 use proc_macro::TokenStream;
 use quote::quote;
 use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
 use syn::{Ident, ItemMod, braced};
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -83,6 +84,129 @@ pub(crate) fn slugify(s: &str) -> String {
     result
 }
 
+/// Parse an optional `tags(a, b)` list following a test or group header.
+/// Returns empty Vec if no `tags` keyword is present.
+pub(crate) fn parse_tags(input: ParseStream) -> syn::Result<Vec<String>> {
+    if !input.peek(Ident) || !input.fork().parse::<Ident>().is_ok_and(|kw| kw == "tags") {
+        return Ok(Vec::new());
+    }
+    let _: Ident = input.parse()?;
+    let content;
+    syn::parenthesized!(content in input);
+    let tags = Punctuated::<Ident, syn::Token![,]>::parse_terminated(&content)?;
+    Ok(tags.iter().map(|t| t.to_string()).collect())
+}
+
+/// Merge group tags with a test's own tags, preserving order and dropping duplicates.
+pub(crate) fn merge_tags(group: &[String], test: &[String]) -> Vec<String> {
+    let mut merged: Vec<String> = Vec::new();
+    for tag in group.iter().chain(test) {
+        if !merged.contains(tag) {
+            merged.push(tag.clone());
+        }
+    }
+    merged
+}
+
+/// Prefix of the metadata record embedded in every generated test.
+///
+/// `cargo-spectacular` scans test binaries for this marker to discover tags,
+/// descriptions and source locations without running anything. The record is
+/// a JSON object terminated by `\0`.
+const META_MARKER: &str = "spectacular:meta:";
+
+/// What a generated test records about itself for `cargo-spectacular`.
+pub(crate) struct TestMeta<'a> {
+    pub(crate) fn_name: &'a Ident,
+    /// The original `it` text; `None` for attribute-style tests.
+    pub(crate) description: Option<&'a str>,
+    /// The `describe` text or module name of the enclosing group.
+    pub(crate) group: &'a str,
+    pub(crate) tags: &'a [String],
+    /// Where the test is declared.
+    pub(crate) span: proc_macro2::Span,
+    /// Declared with `bench` or `#[bench]`.
+    pub(crate) bench: bool,
+}
+
+impl TestMeta<'_> {
+    /// Statement that embeds the record in the binary.
+    ///
+    /// The record is kept alive through `black_box` so the linker can't discard it.
+    /// `module_path!()` supplies the full path, so the runner can match the record
+    /// to the libtest name wherever the group is nested.
+    pub(crate) fn embed(&self) -> proc_macro2::TokenStream {
+        let fn_name = self.fn_name;
+        let mut fields = format!(",\"group\":{}", json_string(self.group));
+        if let Some(desc) = self.description {
+            fields.push_str(&format!(",\"description\":{}", json_string(desc)));
+        }
+        // Line 0 means the compiler can't report span locations (Rust < 1.88)
+        let start = self.span.start();
+        if start.line > 0 {
+            fields.push_str(&format!(
+                ",\"file\":{},\"line\":{}",
+                json_string(&self.span.file()),
+                start.line
+            ));
+        }
+        if self.bench {
+            fields.push_str(",\"bench\":true");
+        }
+        let tags = self
+            .tags
+            .iter()
+            .map(|t| json_string(t))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let head = format!("{META_MARKER}{{\"path\":\"");
+        let tail = format!("::{fn_name}\"{fields},\"tags\":[{tags}]}}\0");
+        quote! {
+            ::std::hint::black_box(::std::concat!(#head, ::std::module_path!(), #tail));
+        }
+    }
+}
+
+/// Quote and escape `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Run a benchmark's body with its `Bencher` bound to `pat`, then report the measurement.
+///
+/// The path matches the metadata record's, so the runner can tell which test measured it.
+pub(crate) fn wrap_bench_body(
+    fn_name: &Ident,
+    pat: &syn::Pat,
+    ty: Option<&syn::Type>,
+    body: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let tail = format!("::{fn_name}");
+    let ty = ty.map(|ty| quote! { : #ty });
+    quote! {
+        let mut __spectacular_bencher = ::spectacular::Bencher::new(
+            ::std::concat!(::std::module_path!(), #tail)
+        );
+        {
+            let #pat #ty = &mut __spectacular_bencher;
+            #body
+        }
+        __spectacular_bencher.report();
+    }
+}
+
 pub(crate) fn wrap_test_body(
     pre: proc_macro2::TokenStream,
     body: proc_macro2::TokenStream,
@@ -284,6 +408,22 @@ pub fn test_suite(attr_input: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
+/// Marker for test tags in [`test_suite`]. See [`spectacular::tags`](https://docs.rs/spectacular).
+#[proc_macro_attribute]
+pub fn tags(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    // Inside a #[test_suite] module the attribute is consumed before it expands.
+    // Reaching a module here means it sits above #[test_suite] (or has none).
+    if let Ok(module) = syn::parse::<ItemMod>(item.clone()) {
+        return syn::Error::new_spanned(
+            &module.ident,
+            "#[tags] on a module must be placed below #[test_suite]",
+        )
+        .to_compile_error()
+        .into();
+    }
+    item
+}
+
 /// Marker for once-per-group setup in [`test_suite`]. See [`spectacular::before`](https://docs.rs/spectacular).
 #[proc_macro_attribute]
 pub fn before(_attr: TokenStream, item: TokenStream) -> TokenStream {
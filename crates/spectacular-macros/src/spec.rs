@@ -5,8 +5,8 @@ use syn::parse::{Parse, ParseStream};
 use syn::{Ident, LitStr, Token, braced};
 
 use crate::{
-    Runtime, default_runtime, is_type_infer, ref_inner_type, slugify, wrap_async_test_body,
-    wrap_test_body,
+    Runtime, TestMeta, default_runtime, is_type_infer, merge_tags, parse_tags, ref_inner_type,
+    slugify, wrap_async_test_body, wrap_bench_body, wrap_test_body,
 };
 
 /// A parsed parameter from pipe syntax: `|name: &Type, name2: Type|`
@@ -16,6 +16,12 @@ pub(crate) struct PipeParam {
     is_ref: bool,
 }
 
+/// The `Bencher` a `bench` item takes first: `|b|` or `|b: &mut Bencher|`.
+pub(crate) struct BenchParam {
+    pat: syn::Pat,
+    ty: Option<syn::Type>,
+}
+
 pub(crate) enum SpecItem {
     Suite,
     Runtime(Runtime),
@@ -30,14 +36,43 @@ pub(crate) enum SpecItem {
     //         body                     async  return_type          input_params
     AfterEach(proc_macro2::TokenStream, bool, Vec<PipeParam>),
     //        body                      async  params
-    It(String, proc_macro2::TokenStream, bool, Vec<PipeParam>),
-    //         body                      async  params
+    It(
+        LitStr,
+        Vec<String>,
+        proc_macro2::TokenStream,
+        bool,
+        Vec<PipeParam>,
+    ),
+    // desc  tags  body                      async  params
+    Bench(
+        LitStr,
+        Vec<String>,
+        Box<BenchParam>,
+        proc_macro2::TokenStream,
+        Vec<PipeParam>,
+    ),
+    // desc  tags  bencher     body                      params
     Other(proc_macro2::TokenStream),
 }
 
+/// A test case collected from an `it` item, ready for code generation.
+struct SpecTest {
+    desc: LitStr,
+    fn_name: Ident,
+    tags: Vec<String>,
+    body: proc_macro2::TokenStream,
+    is_async: bool,
+    params: Vec<PipeParam>,
+    /// Set for `bench` items.
+    bencher: Option<BenchParam>,
+}
+
 pub(crate) struct SpecModule {
     vis: syn::Visibility,
     ident: Ident,
+    /// The `describe` text, or the module name for `mod name`.
+    group: String,
+    tags: Vec<String>,
     items: Vec<SpecItem>,
 }
 
@@ -48,6 +83,11 @@ fn parse_pipe_params(input: ParseStream) -> syn::Result<Vec<PipeParam>> {
         return Ok(Vec::new());
     }
     input.parse::<Token![|]>()?;
+    parse_params_until_pipe(input)
+}
+
+/// Parse the rest of a pipe list, through the closing `|`.
+fn parse_params_until_pipe(input: ParseStream) -> syn::Result<Vec<PipeParam>> {
     let mut params = Vec::new();
     while !input.peek(Token![|]) {
         let pat: syn::Pat = syn::Pat::parse_single(input)?;
@@ -63,6 +103,27 @@ fn parse_pipe_params(input: ParseStream) -> syn::Result<Vec<PipeParam>> {
     Ok(params)
 }
 
+/// Parse a `bench` item's params: the bencher, then any context params.
+/// `|b, doc: &Document|`
+fn parse_bench_params(input: ParseStream) -> syn::Result<(BenchParam, Vec<PipeParam>)> {
+    if !input.peek(Token![|]) {
+        return Err(input.error("expected `|b|` naming the bencher after the `bench` description"));
+    }
+    input.parse::<Token![|]>()?;
+    let pat = syn::Pat::parse_single(input)?;
+    let ty = if input.peek(Token![:]) {
+        input.parse::<Token![:]>()?;
+        Some(input.parse()?)
+    } else {
+        None
+    };
+    if input.peek(Token![,]) {
+        input.parse::<Token![,]>()?;
+    }
+    let params = parse_params_until_pipe(input)?;
+    Ok((BenchParam { pat, ty }, params))
+}
+
 /// Parse optional `-> Type` return type.
 fn parse_return_type(input: ParseStream) -> syn::Result<Option<syn::Type>> {
     if input.peek(Token![->]) {
@@ -79,9 +140,11 @@ impl Parse for SpecModule {
         let vis: syn::Visibility = input.parse()?;
 
         // Accept either `mod ident` or `describe "string literal"`
-        let ident: Ident = if input.peek(Token![mod]) {
+        let (ident, group) = if input.peek(Token![mod]) {
             input.parse::<Token![mod]>()?;
-            input.parse()?
+            let ident: Ident = input.parse()?;
+            let group = ident.to_string();
+            (ident, group)
         } else {
             let kw: Ident = input.parse()?;
             if kw != "describe" {
@@ -89,8 +152,9 @@ impl Parse for SpecModule {
             }
             let desc: LitStr = input.parse()?;
             let slug = slugify(&desc.value());
-            Ident::new(&slug, desc.span())
+            (Ident::new(&slug, desc.span()), desc.value())
         };
+        let tags = parse_tags(input)?;
 
         let content;
         braced!(content in input);
@@ -109,10 +173,11 @@ impl Parse for SpecModule {
                             let _: Token![async] = content.parse()?;
                             let _: Ident = content.parse()?;
                             let desc: LitStr = content.parse()?;
+                            let tags = parse_tags(&content)?;
                             let params = parse_pipe_params(&content)?;
                             let body;
                             braced!(body in content);
-                            items.push(SpecItem::It(desc.value(), body.parse()?, true, params));
+                            items.push(SpecItem::It(desc, tags, body.parse()?, true, params));
                             continue;
                         }
                         "before_each" => {
@@ -170,10 +235,27 @@ impl Parse for SpecModule {
                     "it" => {
                         let _: Ident = content.parse()?;
                         let desc: LitStr = content.parse()?;
+                        let tags = parse_tags(&content)?;
                         let params = parse_pipe_params(&content)?;
                         let body;
                         braced!(body in content);
-                        items.push(SpecItem::It(desc.value(), body.parse()?, false, params));
+                        items.push(SpecItem::It(desc, tags, body.parse()?, false, params));
+                        continue;
+                    }
+                    "bench" => {
+                        let _: Ident = content.parse()?;
+                        let desc: LitStr = content.parse()?;
+                        let tags = parse_tags(&content)?;
+                        let (bencher, params) = parse_bench_params(&content)?;
+                        let body;
+                        braced!(body in content);
+                        items.push(SpecItem::Bench(
+                            desc,
+                            tags,
+                            Box::new(bencher),
+                            body.parse()?,
+                            params,
+                        ));
                         continue;
                     }
                     "before_each" => {
@@ -228,7 +310,13 @@ impl Parse for SpecModule {
             items.push(SpecItem::Other(quote! { #item }));
         }
 
-        Ok(SpecModule { vis, ident, items })
+        Ok(SpecModule {
+            vis,
+            ident,
+            group,
+            tags,
+            items,
+        })
     }
 }
 
@@ -254,13 +342,7 @@ pub(crate) fn expand(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2
     let mut after_each_body: Option<proc_macro2::TokenStream> = None;
     let mut after_each_is_async = false;
     let mut after_each_params: Vec<PipeParam> = Vec::new();
-    let mut tests: Vec<(
-        String,
-        Ident,
-        proc_macro2::TokenStream,
-        bool,
-        Vec<PipeParam>,
-    )> = Vec::new();
+    let mut tests: Vec<SpecTest> = Vec::new();
     let mut other_items: Vec<proc_macro2::TokenStream> = Vec::new();
 
     for item in parsed.items {
@@ -318,9 +400,27 @@ pub(crate) fn expand(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2
                 after_each_is_async = is_async;
                 after_each_params = params;
             }
-            SpecItem::It(desc, body, is_async, params) => {
-                let fn_name = format_ident!("{}", slugify(&desc));
-                tests.push((desc, fn_name, body, is_async, params));
+            SpecItem::It(desc, tags, body, is_async, params) => {
+                tests.push(SpecTest {
+                    fn_name: format_ident!("{}", slugify(&desc.value())),
+                    desc,
+                    tags: merge_tags(&parsed.tags, &tags),
+                    body,
+                    is_async,
+                    params,
+                    bencher: None,
+                });
+            }
+            SpecItem::Bench(desc, tags, bencher, body, params) => {
+                tests.push(SpecTest {
+                    fn_name: format_ident!("{}", slugify(&desc.value())),
+                    desc,
+                    tags: merge_tags(&parsed.tags, &tags),
+                    body,
+                    is_async: false,
+                    params,
+                    bencher: Some(*bencher),
+                });
             }
             SpecItem::Other(tokens) => {
                 other_items.push(tokens);
@@ -329,9 +429,7 @@ pub(crate) fn expand(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2
     }
 
     // Validate: async items require a runtime
-    let any_async = tests.iter().any(|(_, _, _, is_async, _)| *is_async)
-        || before_each_is_async
-        || after_each_is_async;
+    let any_async = tests.iter().any(|t| t.is_async) || before_each_is_async || after_each_is_async;
 
     let runtime = runtime.or_else(default_runtime);
 
@@ -361,7 +459,7 @@ pub(crate) fn expand(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2
             .iter()
             .chain(before_each_params.iter())
             .chain(after_each_params.iter())
-            .chain(tests.iter().flat_map(|(_, _, _, _, p)| p.iter()));
+            .chain(tests.iter().flat_map(|t| t.params.iter()));
         for p in ref_sources {
             if p.is_ref
                 && let Some(inner) = ref_inner_type(&p.ty)
@@ -382,7 +480,7 @@ pub(crate) fn expand(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2
     // Detect inline mode from consumers: tests or after_each have `_`-typed params
     let has_infer_consumers = tests
         .iter()
-        .any(|(_, _, _, _, params)| params.iter().any(|p| is_type_infer(&p.ty)))
+        .any(|t| t.params.iter().any(|p| is_type_infer(&p.ty)))
         || after_each_params.iter().any(|p| is_type_infer(&p.ty));
     let before_each_needs_inline = !has_before_each_ctx && has_infer_consumers;
     let after_each_needs_inline = after_each_params.iter().any(|p| is_type_infer(&p.ty));
@@ -492,11 +590,29 @@ pub(crate) fn expand(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2
 
     let test_fn_defs: Vec<proc_macro2::TokenStream> = tests
         .iter()
-        .map(|(_desc, fn_name, body, is_async, test_params)| {
+        .map(|test| {
+            let SpecTest {
+                desc,
+                fn_name,
+                tags,
+                body,
+                is_async,
+                params: test_params,
+                bencher,
+            } = test;
             // A test needs async if it's declared async or any hook it uses is async
             let test_needs_async = *is_async || before_each_is_async || after_each_is_async;
 
-            let mut pre = proc_macro2::TokenStream::new();
+            let description = desc.value();
+            let mut pre = TestMeta {
+                fn_name,
+                description: Some(&description),
+                group: &parsed.group,
+                tags,
+                span: desc.span(),
+                bench: bencher.is_some(),
+            }
+            .embed();
             let mut post = proc_macro2::TokenStream::new();
 
             // --- Suite before ---
@@ -663,6 +779,12 @@ pub(crate) fn expand(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2
             } else {
                 quote! { #(#ref_bindings)* #body }
             };
+            let body_with_bindings = match bencher {
+                Some(BenchParam { pat, ty }) => {
+                    wrap_bench_body(fn_name, pat, ty.as_ref(), body_with_bindings)
+                }
+                None => body_with_bindings,
+            };
 
             // --- after_each ---
             if has_after_each {
@@ -0,0 +1,126 @@
+//! The measurement loop behind `bench` items.
+
+use std::fs::OpenOptions;
+use std::hint::black_box;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Set by `cargo spectacular --bench` to the file measurements are added to.
+const BENCH_ENV: &str = "SPECTACULAR_BENCH";
+
+/// How long one sample should take; short routines run many times per sample.
+const SAMPLE_TIME: Duration = Duration::from_millis(5);
+
+/// Samples taken at most, and at least, however long they take.
+const MAX_SAMPLES: usize = 50;
+const MIN_SAMPLES: usize = 5;
+
+/// Time after which no more samples are taken once `MIN_SAMPLES` are in.
+const BUDGET: Duration = Duration::from_secs(3);
+
+/// Times a routine inside a `bench` item.
+///
+/// In a normal test run the routine passed to [`iter`](Bencher::iter) runs
+/// once, so benchmarks are checked like any other test. Under
+/// `cargo spectacular --bench` it runs in timed batches instead, and the
+/// mean time per iteration, its deviation and the number of iterations are
+/// reported to the runner.
+pub struct Bencher {
+    path: &'static str,
+    /// Where to add the measurement; `None` outside `--bench`.
+    results: Option<PathBuf>,
+    result: Option<Measurement>,
+}
+
+struct Measurement {
+    /// Nanoseconds per iteration.
+    mean: f64,
+    deviation: f64,
+    iterations: u64,
+}
+
+impl Bencher {
+    #[doc(hidden)]
+    pub fn new(path: &'static str) -> Self {
+        Self {
+            path,
+            results: std::env::var_os(BENCH_ENV).map(PathBuf::from),
+            result: None,
+        }
+    }
+
+    /// Time `routine`; its result is passed through `black_box` so it isn't
+    /// optimized away.
+    ///
+    /// Only the routine is timed: setup done before calling `iter`, including
+    /// the group's `before` and `before_each` hooks, is not measured.
+    pub fn iter<T>(&mut self, mut routine: impl FnMut() -> T) {
+        if self.results.is_none() {
+            black_box(routine());
+            return;
+        }
+
+        // Grow the batch until one takes long enough to time reliably
+        let mut batch: u64 = 1;
+        loop {
+            let time = run_batch(&mut routine, batch);
+            if time >= SAMPLE_TIME || batch >= 1 << 30 {
+                break;
+            }
+            batch *= 2;
+        }
+
+        let started = Instant::now();
+        let mut samples: Vec<f64> = Vec::with_capacity(MAX_SAMPLES);
+        while samples.len() < MAX_SAMPLES
+            && (samples.len() < MIN_SAMPLES || started.elapsed() < BUDGET)
+        {
+            let time = run_batch(&mut routine, batch);
+            samples.push(time.as_nanos() as f64 / batch as f64);
+        }
+
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        self.result = Some(Measurement {
+            mean,
+            deviation: variance.sqrt(),
+            iterations: batch * samples.len() as u64,
+        });
+    }
+
+    /// Add the measurement to the results file as one JSON line.
+    ///
+    /// A file rather than the output, which libtest captures and interleaves.
+    #[doc(hidden)]
+    pub fn report(&self) {
+        let (Some(results), Some(m)) = (&self.results, &self.result) else {
+            return;
+        };
+        let line = format!(
+            "{{\"path\":\"{}\",\"mean\":{},\"deviation\":{},\"iterations\":{}}}\n",
+            self.path, m.mean, m.deviation, m.iterations
+        );
+        // One append per line, so benchmarks finishing together don't mix
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(results)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        if let Err(e) = written {
+            panic!(
+                "could not record the measurement in {}: {e}",
+                results.display()
+            );
+        }
+    }
+}
+
+fn run_batch<T>(routine: &mut impl FnMut() -> T, batch: u64) -> Duration {
+    let start = Instant::now();
+    for _ in 0..batch {
+        black_box(routine());
+    }
+    start.elapsed()
+}
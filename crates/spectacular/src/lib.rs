@@ -272,6 +272,10 @@
 //!
 //! Without `_` params, a void `before_each` is fire-and-forget as usual.
 
+mod bench;
+
+pub use bench::Bencher;
+
 /// Defines suite-level hooks that run across all opted-in test groups.
 ///
 /// Generates a hidden `__spectacular_suite` module containing `before()`,
@@ -308,7 +312,9 @@ pub use spectacular_macros::suite;
 /// Defines a test group using RSpec-style DSL.
 ///
 /// Each `it "description" { body }` block becomes a `#[test]` function whose
-/// name is the slugified description. Groups support `before`, `after`,
+/// name is the slugified description. The original `describe`/`it` text and
+/// source location are embedded as metadata for `cargo spectacular`, which
+/// shows them in place of the test path. Groups support `before`, `after`,
 /// `before_each`, and `after_each` hooks. Add `suite;` to opt into
 /// suite-level hooks defined by [`suite!`].
 ///
@@ -333,6 +339,22 @@ pub use spectacular_macros::suite;
 /// Helper functions, constants, and `use` statements can appear alongside
 /// hooks and test cases.
 ///
+/// ## Tags
+///
+/// Tests and groups accept a `tags(...)` list after their name. Group tags
+/// are inherited by every test in the group:
+///
+/// ```ignore
+/// spec! {
+///     describe "sync engine" tags(integration) {
+///         it "syncs" tags(slow, db) { /* tagged integration, slow, db */ }
+///     }
+/// }
+/// ```
+///
+/// Tags don't change test names. `cargo spectacular --tag slow` and
+/// `--exclude-tag db` select tests by tag.
+///
 /// # Context Injection
 ///
 /// Hooks can return context values using `-> Type` syntax, and receive
@@ -438,6 +460,29 @@ pub use spectacular_macros::suite;
 /// }
 /// # fn main() {}
 /// ```
+///
+/// # Benchmarks
+///
+/// `bench "description" |b| { ... }` declares a benchmark. It takes the
+/// [`Bencher`] first, then any context params, and times the routine passed
+/// to [`Bencher::iter`]. Hooks and context work as for `it`, so fixture
+/// setup happens before the routine and isn't measured. In a normal test
+/// run the routine runs once; `cargo spectacular --bench` measures it.
+///
+/// ```
+/// use spectacular::spec;
+///
+/// spec! {
+///     mod parser {
+///         before -> String { "word ".repeat(1000) }
+///
+///         bench "counts words" |b, doc: &String| {
+///             b.iter(|| doc.split_whitespace().count());
+///         }
+///     }
+/// }
+/// # fn main() {}
+/// ```
 pub use spectacular_macros::spec;
 
 /// Marks a module as a test suite using standard Rust attribute syntax.
@@ -516,6 +561,30 @@ pub use spectacular_macros::spec;
 /// }
 /// # fn main() {}
 /// ```
+///
+/// # Benchmarks
+///
+/// `#[bench]` functions take the [`Bencher`] first, then context params
+/// like a `#[test]`. They run once in a normal test run and are measured
+/// under `cargo spectacular --bench`.
+///
+/// ```
+/// use spectacular::{test_suite, before, Bencher};
+///
+/// #[test_suite]
+/// mod parser {
+///     use super::*;
+///
+///     #[before]
+///     fn load() -> String { "word ".repeat(1000) }
+///
+///     #[bench]
+///     fn counts_words(b: &mut Bencher, doc: &String) {
+///         b.iter(|| doc.split_whitespace().count());
+///     }
+/// }
+/// # fn main() {}
+/// ```
 pub use spectacular_macros::test_suite;
 
 /// Marks a function as a once-per-group setup hook inside a
@@ -794,6 +863,28 @@ pub use spectacular_macros::before_each;
 /// ```
 pub use spectacular_macros::after_each;
 
+/// Tags a test inside a [`#[test_suite]`](macro@test_suite) module.
+///
+/// Tags are plain identifiers. Placed on the module (below `#[test_suite]`),
+/// they are inherited by every test in it. Test names are unchanged;
+/// `cargo spectacular --tag slow` and `--exclude-tag db` select by tag.
+///
+/// ```
+/// use spectacular::{test_suite, tags};
+///
+/// #[test_suite]
+/// #[tags(integration)]
+/// mod sync_engine {
+///     #[test]
+///     #[tags(slow, db)]
+///     fn syncs() {
+///         assert!(true);
+///     }
+/// }
+/// # fn main() {}
+/// ```
+pub use spectacular_macros::tags;
+
 /// Internal helpers used by generated code. Not part of the public API.
 #[doc(hidden)]
 pub mod __internal {
@@ -826,7 +917,10 @@ pub mod __internal {
 /// # fn main() {}
 /// ```
 pub mod prelude {
-    pub use spectacular_macros::{after, after_each, before, before_each, spec, suite, test_suite};
+    pub use crate::Bencher;
+    pub use spectacular_macros::{
+        after, after_each, before, before_each, spec, suite, tags, test_suite,
+    };
 }
 
 #[cfg(test)]
@@ -807,6 +807,14 @@ spec! {
     }
 }
 
+spec! {
+    describe "descriptions with \"quotes\" and \\ slashes" {
+        it "keeps punctuation like 100% & \"friends\"" {
+            assert_eq!(1 + 1, 2);
+        }
+    }
+}
+
 // ===== Inferred before context (void before + explicit &T consumers) =====
 
 // --- spec! style: before with no return type, type inferred from after ---
@@ -1050,3 +1058,199 @@ spec! {
         }
     }
 }
+
+// ===== Tags =====
+
+// --- spec! style: group tags, test tags, and tags alongside params ---
+
+spec! {
+    describe "tagged group" tags(integration) {
+        before -> i32 { 7 }
+
+        it "runs with group tags" {
+            assert_eq!(1 + 1, 2);
+        }
+
+        it "runs with its own tags" tags(slow, db) {
+            assert_eq!(2 * 2, 4);
+        }
+
+        it "combines tags and params" tags(slow) |val: &i32| {
+            assert_eq!(*val, 7);
+        }
+    }
+}
+
+spec! {
+    mod tagged_async {
+        tokio;
+
+        async it "runs tagged async test" tags(network) {
+            assert_eq!(async { 3 }.await, 3);
+        }
+    }
+}
+
+// --- attribute style: module tags inherited, test tags merged ---
+
+static ATTR_TAGGED_BEFORE_EACH: AtomicUsize = AtomicUsize::new(0);
+
+#[test_suite]
+#[tags(integration)]
+mod attr_tagged {
+    use super::*;
+
+    #[before_each]
+    fn setup() {
+        ATTR_TAGGED_BEFORE_EACH.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[test]
+    #[tags(slow, db)]
+    fn tagged_test_keeps_hooks() {
+        assert!(ATTR_TAGGED_BEFORE_EACH.load(Ordering::SeqCst) >= 1);
+    }
+
+    #[test]
+    fn inherits_module_tags() {
+        assert!(ATTR_TAGGED_BEFORE_EACH.load(Ordering::SeqCst) >= 1);
+    }
+}
+
+// --- the merged tags are embedded in the binary for cargo-spectacular ---
+
+/// The metadata record the macros embedded in this binary for the test at `path`.
+fn meta_record(path: &str) -> String {
+    let binary = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+    let marker = "spectacular:meta:";
+    let head = format!("{marker}{{\"path\":\"{path}\"");
+    let start = binary
+        .windows(head.len())
+        .position(|w| w == head.as_bytes())
+        .unwrap_or_else(|| panic!("no metadata record for {path}"))
+        + marker.len();
+    let len = binary[start..].iter().position(|&b| b == 0).unwrap();
+    String::from_utf8(binary[start..start + len].to_vec()).unwrap()
+}
+
+fn embedded_tags(path: &str) -> String {
+    let record = meta_record(path);
+    let (_, tags) = record.split_once("\"tags\":").unwrap();
+    tags.trim_end_matches('}').to_string()
+}
+
+#[test]
+fn group_tags_merge_into_each_test() {
+    assert_eq!(
+        embedded_tags("integration::tagged_group::runs_with_group_tags"),
+        r#"["integration"]"#
+    );
+    assert_eq!(
+        embedded_tags("integration::tagged_group::runs_with_its_own_tags"),
+        r#"["integration","slow","db"]"#
+    );
+    assert_eq!(
+        embedded_tags("integration::tagged_group::combines_tags_and_params"),
+        r#"["integration","slow"]"#
+    );
+    assert_eq!(
+        embedded_tags("integration::tagged_async::runs_tagged_async_test"),
+        r#"["network"]"#
+    );
+}
+
+#[test]
+fn module_tags_are_inherited_by_attribute_tests() {
+    assert_eq!(
+        embedded_tags("integration::attr_tagged::tagged_test_keeps_hooks"),
+        r#"["integration","slow","db"]"#
+    );
+    assert_eq!(
+        embedded_tags("integration::attr_tagged::inherits_module_tags"),
+        r#"["integration"]"#
+    );
+}
+
+// --- descriptions and locations are embedded alongside the tags ---
+
+const EMBEDDED_IT_LINE: u32 = line!() + 3;
+spec! {
+    describe "embedded metadata" {
+        it "records where it is declared" {}
+    }
+}
+
+#[test]
+fn description_and_location_are_embedded() {
+    let record = meta_record("integration::embedded_metadata::records_where_it_is_declared");
+    assert!(
+        record.contains(
+            r#""group":"embedded metadata","description":"records where it is declared""#
+        ),
+        "{record}"
+    );
+    let location = format!(r#""file":"{}","line":{EMBEDDED_IT_LINE}"#, file!());
+    assert!(record.contains(&location), "{record}");
+}
+
+// ===== Benchmarks =====
+
+// --- spec! style: bench reuses before and before_each context ---
+
+static BENCH_BEFORE_EACH: AtomicUsize = AtomicUsize::new(0);
+static BENCH_ROUTINE: AtomicUsize = AtomicUsize::new(0);
+
+spec! {
+    describe "benchmarks" {
+        use super::*;
+
+        before -> Vec<u32> { (0..100).collect() }
+
+        before_each -> usize {
+            BENCH_BEFORE_EACH.fetch_add(1, Ordering::SeqCst);
+            100
+        }
+
+        bench "sums the numbers" |b, numbers: &Vec<u32>, len: usize| {
+            assert_eq!(len, 100);
+            b.iter(|| {
+                BENCH_ROUTINE.fetch_add(1, Ordering::SeqCst);
+                numbers.iter().sum::<u32>()
+            });
+            assert!(BENCH_BEFORE_EACH.load(Ordering::SeqCst) >= 1);
+            assert!(BENCH_ROUTINE.load(Ordering::SeqCst) >= 1);
+        }
+
+        bench "takes a typed bencher" tags(slow) |b: &mut spectacular::Bencher| {
+            b.iter(|| 2 + 2);
+        }
+
+        it "runs next to benchmarks" |numbers: &Vec<u32>, len: usize| {
+            assert_eq!(numbers.len(), len);
+        }
+    }
+}
+
+// --- attribute style: #[bench] takes the bencher first, then context ---
+
+#[test_suite]
+mod attr_benchmarks {
+    use spectacular::Bencher;
+
+    #[before]
+    fn init() -> String {
+        "a large document ".repeat(100)
+    }
+
+    #[bench]
+    fn counts_words(b: &mut Bencher, doc: &String) {
+        let mut words = 0;
+        b.iter(|| words = doc.split_whitespace().count());
+        assert_eq!(words, 300);
+    }
+
+    #[test]
+    fn shares_the_context(doc: &String) {
+        assert!(doc.starts_with("a large"));
+    }
+}